@@ -0,0 +1,524 @@
+use crate::api::AdminApiKey;
+use crate::models::ghost::Tag;
+use serde_json::{json, Value};
+use std::thread;
+use std::time::Duration;
+
+/// Admin API version requested through the `Accept-Version` header.
+const ACCEPT_VERSION: &str = "v5.0";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("Ghost API returned {status}: {message}")]
+    Status {
+        status: u16,
+        error_type: Option<String>,
+        message: String,
+    },
+    #[error("Request to Ghost failed: {0}")]
+    Transport(String),
+    /// The request never reached Ghost
+    #[error("Could not connect to Ghost: {0}")]
+    Connect(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ApiError::Status { status, .. } => Some(*status),
+            ApiError::Transport(_) | ApiError::Connect(_) => None,
+        }
+    }
+
+    /// Rate limits and failed connections are always worth retrying. Server
+    /// errors and dropped connections may come after Ghost already acted on
+    /// the request, so only idempotent requests are retried for those.
+    fn is_retryable(&self, idempotent: bool) -> bool {
+        match self {
+            ApiError::Status { status, .. } => *status == 429 || (idempotent && *status >= 500),
+            ApiError::Transport(_) => idempotent,
+            ApiError::Connect(_) => true,
+        }
+    }
+}
+
+/// How often and how patiently failed requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// First backoff delay, doubled on every further attempt
+    pub base_delay: Duration,
+    /// Upper bound for a single wait, including `Retry-After` values
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = self.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+        retry_after.unwrap_or(backoff).min(self.max_delay)
+    }
+}
+
+/// Request body variants the Admin API accepts.
+enum Payload<'a> {
+    Empty,
+    Json(&'a Value),
+    Multipart { boundary: String, body: Vec<u8> },
+}
+
+/// Thin client for Ghost's `/ghost/api/admin/` endpoints.
+pub struct AdminClient {
+    site_url: String,
+    api_url: String,
+    key: AdminApiKey,
+    agent: ureq::Agent,
+    retry: RetryPolicy,
+}
+
+impl AdminClient {
+    pub fn new(site_url: &str, key: AdminApiKey) -> Self {
+        let site_url = site_url.trim_end_matches('/').to_string();
+        Self {
+            api_url: format!("{}/ghost/api/admin", site_url),
+            site_url,
+            key,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(60))
+                .build(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn site_url(&self) -> &str {
+        &self.site_url
+    }
+
+    pub fn get(&self, path: &str) -> Result<Value, ApiError> {
+        self.send("GET", path, &Payload::Empty)
+    }
+
+    pub fn post(&self, path: &str, body: &Value) -> Result<Value, ApiError> {
+        self.send("POST", path, &Payload::Json(body))
+    }
+
+    pub fn put(&self, path: &str, body: &Value) -> Result<Value, ApiError> {
+        self.send("PUT", path, &Payload::Json(body))
+    }
+
+    pub fn delete(&self, path: &str) -> Result<(), ApiError> {
+        self.send("DELETE", path, &Payload::Empty).map(|_| ())
+    }
+
+    /// Uploads an image through `/images/upload/` and returns the URL Ghost
+    /// stored it under.
+    pub fn upload_image(&self, file_name: &str, data: &[u8]) -> Result<String, ApiError> {
+        let boundary = format!(
+            "gmi-{:x}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+        let mut body = Vec::with_capacity(data.len() + 512);
+        body.extend_from_slice(
+            format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{name}\"\r\n\
+                 Content-Type: {mime}\r\n\r\n",
+                b = boundary,
+                name = file_name.replace('"', "%22"),
+                mime = image_content_type(file_name),
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(
+            format!(
+                "\r\n--{b}\r\nContent-Disposition: form-data; name=\"purpose\"\r\n\r\nimage\r\n--{b}--\r\n",
+                b = boundary
+            )
+            .as_bytes(),
+        );
+
+        let response = self.send(
+            "POST",
+            "/images/upload/",
+            &Payload::Multipart { boundary, body },
+        )?;
+        response["images"][0]["url"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| ApiError::Transport("upload response is missing a url".to_string()))
+    }
+
+    pub fn find_tag(&self, slug: &str) -> Result<Option<Value>, ApiError> {
+        self.find_one(&format!("/tags/slug/{}/", slug), "tags")
+    }
+
+    pub fn create_tag(&self, tag: &Tag) -> Result<Value, ApiError> {
+        let mut body = json!({ "name": tag.name, "slug": tag.slug });
+        for (field, value) in [
+            ("description", &tag.description),
+            ("feature_image", &tag.feature_image),
+            ("meta_title", &tag.meta_title),
+            ("meta_description", &tag.meta_description),
+            ("accent_color", &tag.accent_color),
+        ] {
+            if let Some(value) = value {
+                body[field] = json!(value);
+            }
+        }
+        let response = self.post("/tags/", &json!({ "tags": [body] }))?;
+        Ok(first_resource(response, "tags"))
+    }
+
+    /// Staff users cannot be created over the Admin API, only looked up.
+    pub fn find_user(&self, slug: &str, email: &str) -> Result<Option<Value>, ApiError> {
+        if !email.is_empty() {
+            let path = format!("/users/email/{}/", encode_path_segment(email));
+            if let Some(user) = self.find_one(&path, "users")? {
+                return Ok(Some(user));
+            }
+        }
+        self.find_one(&format!("/users/slug/{}/", encode_path_segment(slug)), "users")
+    }
+
+    /// Creates a post or page. `resource` is `posts` or `pages`; `source_html`
+    /// asks Ghost to convert the `html` field into its editor format.
+    pub fn create_post(
+        &self,
+        resource: &str,
+        post: &Value,
+        source_html: bool,
+    ) -> Result<Value, ApiError> {
+        let query = if source_html { "?source=html" } else { "" };
+        let response = self.post(
+            &format!("/{}/{}", resource, query),
+            &json!({ resource: [post] }),
+        )?;
+        Ok(first_resource(response, resource))
+    }
+
+    pub fn get_post(&self, resource: &str, id: &str) -> Result<Option<Value>, ApiError> {
+        self.find_one(&format!("/{}/{}/", resource, id), resource)
+    }
+
+    /// Updates a post or page. `post` must carry the `updated_at` Ghost last
+    /// reported, otherwise Ghost rejects the edit with a 409.
+    pub fn update_post(
+        &self,
+        resource: &str,
+        id: &str,
+        post: &Value,
+        source_html: bool,
+    ) -> Result<Value, ApiError> {
+        let query = if source_html { "?source=html" } else { "" };
+        let response = self.put(
+            &format!("/{}/{}/{}", resource, id, query),
+            &json!({ resource: [post] }),
+        )?;
+        Ok(first_resource(response, resource))
+    }
+
+    pub fn delete_post(&self, resource: &str, id: &str) -> Result<(), ApiError> {
+        self.delete(&format!("/{}/{}/", resource, id))
+    }
+
+    fn find_one(&self, path: &str, resource: &str) -> Result<Option<Value>, ApiError> {
+        match self.get(path) {
+            Ok(response) => Ok(Some(first_resource(response, resource))),
+            Err(error) if error.status() == Some(404) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn send(&self, method: &str, path: &str, body: &Payload) -> Result<Value, ApiError> {
+        let url = format!("{}{}", self.api_url, path);
+        let mut attempt = 0;
+        loop {
+            let (error, retry_after) = match self.send_once(method, &url, body) {
+                Ok(value) => return Ok(value),
+                Err(failure) => failure,
+            };
+            if !error.is_retryable(method != "POST") || attempt >= self.retry.max_retries {
+                return Err(error);
+            }
+            thread::sleep(self.retry.delay(attempt, retry_after));
+            attempt += 1;
+        }
+    }
+
+    fn send_once(
+        &self,
+        method: &str,
+        url: &str,
+        body: &Payload,
+    ) -> Result<Value, (ApiError, Option<Duration>)> {
+        let issued_at = chrono::Utc::now().timestamp();
+        let request = self
+            .agent
+            .request(method, url)
+            .set(
+                "Authorization",
+                &format!("Ghost {}", self.key.token(issued_at)),
+            )
+            .set("Accept-Version", ACCEPT_VERSION);
+
+        let result = match body {
+            Payload::Empty => request.call(),
+            Payload::Json(body) => request.send_json(body),
+            Payload::Multipart { boundary, body } => request
+                .set(
+                    "Content-Type",
+                    &format!("multipart/form-data; boundary={}", boundary),
+                )
+                .send_bytes(body),
+        };
+
+        match result {
+            Ok(response) => {
+                let text = response
+                    .into_string()
+                    .map_err(|e| (ApiError::Transport(e.to_string()), None))?;
+                if text.trim().is_empty() {
+                    Ok(Value::Null)
+                } else {
+                    serde_json::from_str(&text)
+                        .map_err(|e| (ApiError::Transport(e.to_string()), None))
+                }
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                let retry_after = response
+                    .header("Retry-After")
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                let body: Value = response.into_json().unwrap_or(Value::Null);
+                let error = &body["errors"][0];
+                Err((
+                    ApiError::Status {
+                        status,
+                        error_type: error["type"].as_str().map(str::to_string),
+                        message: error["message"]
+                            .as_str()
+                            .unwrap_or("no error message")
+                            .to_string(),
+                    },
+                    retry_after,
+                ))
+            }
+            Err(ureq::Error::Transport(transport)) => {
+                let error = match transport.kind() {
+                    ureq::ErrorKind::Dns
+                    | ureq::ErrorKind::ConnectionFailed
+                    | ureq::ErrorKind::ProxyConnect => ApiError::Connect(transport.to_string()),
+                    _ => ApiError::Transport(transport.to_string()),
+                };
+                Err((error, None))
+            }
+        }
+    }
+}
+
+fn first_resource(mut response: Value, resource: &str) -> Value {
+    response
+        .get_mut(resource)
+        .and_then(|list| list.get_mut(0))
+        .map(Value::take)
+        .unwrap_or(Value::Null)
+}
+
+/// Percent-encodes everything but unreserved characters, so emails such as
+/// `a+b@x.org` survive as one path segment.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn image_content_type(file_name: &str) -> &'static str {
+    let extension = file_name.rsplit('.').next().unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockServer;
+
+    fn client(server: &MockServer) -> AdminClient {
+        let key = AdminApiKey::parse("key:736563726574").unwrap();
+        AdminClient::new(&server.url(), key).with_retry_policy(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        })
+    }
+
+    #[test]
+    fn test_requests_are_signed() {
+        let server = MockServer::start(|_| (200, json!({ "tags": [{ "id": "t1" }] })));
+        let tag = client(&server).find_tag("rust").unwrap().unwrap();
+
+        assert_eq!(tag["id"], "t1");
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].url, "/ghost/api/admin/tags/slug/rust/");
+        assert!(requests[0].authorization.starts_with("Ghost "));
+        assert_eq!(requests[0].authorization.matches('.').count(), 2);
+    }
+
+    #[test]
+    fn test_retries_rate_limits_and_server_errors() {
+        let server = MockServer::start(|request| match request.index {
+            0 => (503, json!({})),
+            _ => (200, json!({ "site": { "title": "Blog" } })),
+        });
+        assert_eq!(client(&server).get("/site/").unwrap()["site"]["title"], "Blog");
+        assert_eq!(server.requests().len(), 2);
+
+        let server = MockServer::start(|request| match request.index {
+            0 | 1 => (
+                429,
+                json!({ "errors": [{ "message": "Too many requests" }] }),
+            ),
+            _ => (201, json!({ "posts": [{ "id": "p1" }] })),
+        });
+        let post = client(&server)
+            .create_post("posts", &json!({ "title": "Hi" }), true)
+            .unwrap();
+
+        assert_eq!(post["id"], "p1");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].url, "/ghost/api/admin/posts/?source=html");
+        assert_eq!(requests[2].body["posts"][0]["title"], "Hi");
+    }
+
+    #[test]
+    fn test_does_not_repeat_posts_ghost_may_have_created() {
+        let server = MockServer::start(|_| (503, json!({})));
+        let error = client(&server)
+            .create_post("posts", &json!({ "title": "Hi" }), true)
+            .unwrap_err();
+        assert_eq!(error.status(), Some(503));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_gives_up_after_max_retries_and_on_client_errors() {
+        let server = MockServer::start(|_| (500, json!({})));
+        let error = client(&server).get("/site/").unwrap_err();
+        assert_eq!(error.status(), Some(500));
+        assert_eq!(server.requests().len(), 3);
+
+        let server = MockServer::start(|_| {
+            (
+                422,
+                json!({ "errors": [{ "message": "Validation error", "type": "ValidationError" }] }),
+            )
+        });
+        let error = client(&server).post("/posts/", &json!({})).unwrap_err();
+        assert_eq!(server.requests().len(), 1);
+        assert!(error.to_string().contains("Validation error"));
+    }
+
+    #[test]
+    fn test_missing_resources() {
+        let server =
+            MockServer::start(|_| (404, json!({ "errors": [{ "message": "Not found" }] })));
+        assert!(client(&server)
+            .find_user("jane", "jane@x.org")
+            .unwrap()
+            .is_none());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_find_user_encodes_the_email() {
+        let server = MockServer::start(|_| (200, json!({ "users": [{ "id": "u1" }] })));
+        client(&server).find_user("a-b", "a+b@x.org").unwrap().unwrap();
+        assert_eq!(server.requests()[0].url, "/ghost/api/admin/users/email/a%2Bb%40x.org/");
+    }
+
+    #[test]
+    fn test_create_tag_sends_its_colour() {
+        let server = MockServer::start(|_| (201, json!({ "tags": [{ "id": "t1" }] })));
+        let tag = Tag {
+            name: "Rust".into(),
+            slug: "rust".into(),
+            accent_color: Some("#dea584".into()),
+            ..Default::default()
+        };
+        client(&server).create_tag(&tag).unwrap();
+
+        let body = &server.requests()[0].body["tags"][0];
+        assert_eq!(body["accent_color"], "#dea584");
+        assert!(body.get("description").is_none());
+    }
+
+    #[test]
+    fn test_upload_image() {
+        let server = MockServer::start(|_| {
+            (
+                201,
+                json!({ "images": [{ "url": "https://site/content/images/2024/01/a.png" }] }),
+            )
+        });
+        let url = client(&server)
+            .upload_image("a.png", b"\x89PNG data")
+            .unwrap();
+
+        assert_eq!(url, "https://site/content/images/2024/01/a.png");
+        let request = &server.requests()[0];
+        assert_eq!(request.url, "/ghost/api/admin/images/upload/");
+        assert!(request
+            .content_type
+            .starts_with("multipart/form-data; boundary="));
+        let body = String::from_utf8_lossy(&request.raw_body);
+        assert!(body.contains("name=\"file\"; filename=\"a.png\"\r\nContent-Type: image/png"));
+        assert!(request.raw_body.windows(9).any(|w| w == b"\x89PNG data"));
+        assert!(body.contains("name=\"purpose\"\r\n\r\nimage"));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+        };
+        assert_eq!(policy.delay(0, None), Duration::from_secs(1));
+        assert_eq!(policy.delay(2, None), Duration::from_secs(4));
+        assert_eq!(policy.delay(5, None), Duration::from_secs(10));
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(7))),
+            Duration::from_secs(7)
+        );
+    }
+}
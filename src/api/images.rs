@@ -0,0 +1,299 @@
+use crate::api::AdminClient;
+use crate::models::ghost::GhostImport;
+use crate::processors::{AssetManifest, MarkdownProcessor};
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Remembers which files were already uploaded to which site, keyed by the
+/// SHA-256 of their content, so re-runs skip unchanged images.
+#[derive(Debug, Default)]
+pub struct UploadCache {
+    path: Option<PathBuf>,
+    /// Site URL -> content hash -> uploaded URL
+    sites: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl UploadCache {
+    /// Loads the cache stored at `path`, starting empty when it does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        let sites = if path.exists() {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read upload cache {:?}", path))?;
+            serde_json::from_str(&json)
+                .with_context(|| format!("Upload cache {:?} is not valid JSON", path))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            sites,
+        })
+    }
+
+    pub fn get(&self, site: &str, hash: &str) -> Option<&str> {
+        self.sites.get(site)?.get(hash).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, site: &str, hash: &str, url: &str) {
+        self.sites
+            .entry(site.to_string())
+            .or_default()
+            .insert(hash.to_string(), url.to_string());
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let json = serde_json::to_string_pretty(&self.sites)?;
+            std::fs::write(path, json)
+                .with_context(|| format!("Failed to write upload cache {:?}", path))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UploadReport {
+    /// Import URL (`__GHOST_URL__/content/images/...`) -> uploaded URL
+    pub urls: HashMap<String, String>,
+    pub uploaded: Vec<PathBuf>,
+    pub cached: Vec<PathBuf>,
+}
+
+/// Uploads the images of an [`AssetManifest`] through the Admin API.
+pub struct ImageUploader<'a> {
+    client: &'a AdminClient,
+    cache: UploadCache,
+}
+
+impl<'a> ImageUploader<'a> {
+    pub fn new(client: &'a AdminClient, cache: UploadCache) -> Self {
+        Self { client, cache }
+    }
+
+    /// Uploads every file the manifest references, skipping content the cache
+    /// already knows for this site. The cache is saved after each upload so an
+    /// interrupted run does not lose the files it already sent.
+    pub fn upload(&mut self, manifest: &AssetManifest) -> Result<UploadReport> {
+        let site = self.client.site_url().to_string();
+        let mut report = UploadReport::default();
+
+        for (import_url, path) in manifest.by_url() {
+            let data =
+                std::fs::read(path).with_context(|| format!("Failed to read image {:?}", path))?;
+            let hash = hex::encode(Sha256::digest(&data));
+
+            let url = match self.cache.get(&site, &hash) {
+                Some(url) => {
+                    report.cached.push(path.clone());
+                    url.to_string()
+                }
+                None => {
+                    let file_name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "image".to_string());
+                    let url = self
+                        .client
+                        .upload_image(&file_name, &data)
+                        .with_context(|| format!("Failed to upload image {:?}", path))?;
+                    self.cache.insert(&site, &hash, &url);
+                    self.cache.save()?;
+                    report.uploaded.push(path.clone());
+                    url
+                }
+            };
+            report.urls.insert(import_url.clone(), url);
+        }
+
+        Ok(report)
+    }
+}
+
+/// Points image references in the posts, tags and users at the URLs they
+/// were uploaded to. Only whole values are replaced: `src` and `href`
+/// attributes in HTML, string values and Markdown image references in
+/// Lexical and mobiledoc, and the image fields themselves.
+pub fn replace_image_urls(import: &mut GhostImport, urls: &HashMap<String, String>) {
+    for post in &mut import.data.posts {
+        if let Some(html) = post.html.as_mut() {
+            *html = replace_attribute_urls(html, urls);
+        }
+        for document in [&mut post.lexical, &mut post.mobiledoc].into_iter().flatten() {
+            if let Ok(mut value) = serde_json::from_str::<Value>(document) {
+                replace_json_urls(&mut value, None, urls);
+                *document = value.to_string();
+            }
+        }
+        replace_whole_url(&mut post.feature_image, urls);
+    }
+    for meta in &mut import.data.posts_meta {
+        replace_whole_url(&mut meta.og_image, urls);
+        replace_whole_url(&mut meta.twitter_image, urls);
+    }
+    for tag in &mut import.data.tags {
+        replace_whole_url(&mut tag.feature_image, urls);
+    }
+    for user in &mut import.data.users {
+        replace_whole_url(&mut user.profile_image, urls);
+        replace_whole_url(&mut user.cover_image, urls);
+    }
+}
+
+fn replace_whole_url(field: &mut Option<String>, urls: &HashMap<String, String>) {
+    if let Some(url) = field.as_ref().and_then(|value| urls.get(value)) {
+        *field = Some(url.clone());
+    }
+}
+
+fn replace_attribute_urls(html: &str, urls: &HashMap<String, String>) -> String {
+    let re = Regex::new(r#"\b((?:src|href)=)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    re.replace_all(html, |caps: &Captures| {
+        let (value, quote) = match caps.get(2) {
+            Some(m) => (m.as_str(), '"'),
+            None => (caps.get(3).map_or("", |m| m.as_str()), '\''),
+        };
+        match urls.get(value) {
+            Some(url) => format!("{}{}{}{}", &caps[1], quote, url, quote),
+            None => caps[0].to_string(),
+        }
+    })
+    .to_string()
+}
+
+/// Replaces string values that are exactly an uploaded URL, and image
+/// references inside the `markdown` and `html` of cards.
+fn replace_json_urls(value: &mut Value, key: Option<&str>, urls: &HashMap<String, String>) {
+    match value {
+        Value::String(text) => {
+            if let Some(url) = urls.get(text.as_str()) {
+                *text = url.clone();
+            } else if key == Some("markdown") {
+                *text = MarkdownProcessor::rewrite_image_urls(text, urls);
+            } else if key == Some("html") {
+                *text = replace_attribute_urls(text, urls);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                replace_json_urls(item, None, urls);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, item) in fields.iter_mut() {
+                replace_json_urls(item, Some(key), urls);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockServer;
+    use crate::api::AdminApiKey;
+    use crate::models::ghost::Post;
+    use serde_json::json;
+
+    #[test]
+    fn test_upload_uses_content_hash_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("a.png"), b"same").unwrap();
+        std::fs::write(root.join("b.png"), b"same").unwrap();
+        std::fs::write(root.join("c.png"), b"other").unwrap();
+
+        let resolver = crate::processors::AssetResolver::new(root);
+        let post = root.join("post.md");
+        let mut manifest = AssetManifest::new();
+        manifest.add_post(
+            "post.md",
+            ["a.png", "b.png", "c.png"]
+                .iter()
+                .filter_map(|src| resolver.resolve(src, &post))
+                .collect(),
+        );
+
+        let server = MockServer::start(|request| {
+            let url = format!("https://cdn.site/{}.png", request.index);
+            (201, json!({ "images": [{ "url": url }] }))
+        });
+        let client = AdminClient::new(&server.url(), AdminApiKey::parse("k:00").unwrap());
+        let cache_path = root.join("uploads.json");
+
+        let report = ImageUploader::new(&client, UploadCache::load(&cache_path).unwrap())
+            .upload(&manifest)
+            .unwrap();
+        assert_eq!(report.uploaded.len(), 2);
+        assert_eq!(report.cached.len(), 1);
+        assert_eq!(
+            report.urls["__GHOST_URL__/content/images/a.png"],
+            report.urls["__GHOST_URL__/content/images/b.png"]
+        );
+        assert_eq!(server.requests().len(), 2);
+
+        let rerun = ImageUploader::new(&client, UploadCache::load(&cache_path).unwrap())
+            .upload(&manifest)
+            .unwrap();
+        assert!(rerun.uploaded.is_empty());
+        assert_eq!(rerun.urls, report.urls);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_replace_image_urls() {
+        let mut import =
+            crate::processors::GhostExporter::create_export(vec![], None, vec![]).unwrap();
+        import.data.posts.push(Post {
+            html: Some(
+                "<img src=\"__GHOST_URL__/content/images/a.png\"> <a href='__GHOST_URL__/content/images/a.png.bak'>x</a> <img src=\"/x/__GHOST_URL__/content/images/a.png?v=2\">"
+                    .to_string(),
+            ),
+            lexical: Some(
+                json!({ "src": "__GHOST_URL__/content/images/a.png.webp", "caption": "see __GHOST_URL__/content/images/a.png" })
+                    .to_string(),
+            ),
+            mobiledoc: Some(
+                json!({ "cards": [["markdown", { "markdown": "![a](__GHOST_URL__/content/images/a.png) `__GHOST_URL__/content/images/a.png`" }]] })
+                    .to_string(),
+            ),
+            feature_image: Some("__GHOST_URL__/content/images/a.png".to_string()),
+            ..Default::default()
+        });
+        import.data.tags[0].feature_image = Some("__GHOST_URL__/content/images/a.png".to_string());
+        import.data.users[0].profile_image = Some("__GHOST_URL__/content/images/a.png.webp".to_string());
+        let urls = HashMap::from([
+            (
+                "__GHOST_URL__/content/images/a.png".to_string(),
+                "https://cdn/a.png".to_string(),
+            ),
+            (
+                "__GHOST_URL__/content/images/a.png.webp".to_string(),
+                "https://cdn/a.webp".to_string(),
+            ),
+        ]);
+
+        replace_image_urls(&mut import, &urls);
+
+        let post = &import.data.posts[0];
+        assert_eq!(
+            post.html.as_deref(),
+            Some("<img src=\"https://cdn/a.png\"> <a href='__GHOST_URL__/content/images/a.png.bak'>x</a> <img src=\"/x/__GHOST_URL__/content/images/a.png?v=2\">")
+        );
+        let lexical: Value = serde_json::from_str(post.lexical.as_deref().unwrap()).unwrap();
+        assert_eq!(lexical["src"], "https://cdn/a.webp");
+        assert_eq!(lexical["caption"], "see __GHOST_URL__/content/images/a.png");
+        let mobiledoc: Value = serde_json::from_str(post.mobiledoc.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            mobiledoc["cards"][0][1]["markdown"],
+            "![a](https://cdn/a.png) `__GHOST_URL__/content/images/a.png`"
+        );
+        assert_eq!(post.feature_image.as_deref(), Some("https://cdn/a.png"));
+        assert_eq!(import.data.tags[0].feature_image.as_deref(), Some("https://cdn/a.png"));
+        assert_eq!(import.data.users[0].profile_image.as_deref(), Some("https://cdn/a.webp"));
+    }
+}
@@ -0,0 +1,105 @@
+//! A local stand-in for a Ghost site, used to exercise the Admin API client.
+
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    /// Position of the request in arrival order, starting at 0
+    pub index: usize,
+    pub method: String,
+    pub url: String,
+    pub authorization: String,
+    pub content_type: String,
+    /// The body parsed as JSON, or `Null` when it is not JSON
+    pub body: Value,
+    pub raw_body: Vec<u8>,
+}
+
+pub struct MockServer {
+    server: Arc<tiny_http::Server>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Serves every request with the status and JSON body `respond` returns.
+    pub fn start<F>(respond: F) -> Self
+    where
+        F: Fn(&RecordedRequest) -> (u16, Value) + Send + 'static,
+    {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let handle = {
+            let server = Arc::clone(&server);
+            let requests = Arc::clone(&requests);
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let authorization = header(&request, "Authorization");
+                    let content_type = header(&request, "Content-Type");
+                    let mut raw_body = Vec::new();
+                    request.as_reader().read_to_end(&mut raw_body).unwrap();
+
+                    let recorded = {
+                        let mut requests = requests.lock().unwrap();
+                        let recorded = RecordedRequest {
+                            index: requests.len(),
+                            method: request.method().to_string(),
+                            url: request.url().to_string(),
+                            authorization,
+                            content_type,
+                            body: serde_json::from_slice(&raw_body).unwrap_or(Value::Null),
+                            raw_body,
+                        };
+                        requests.push(recorded.clone());
+                        recorded
+                    };
+
+                    let (status, body) = respond(&recorded);
+                    let response = tiny_http::Response::from_string(body.to_string())
+                        .with_status_code(status)
+                        .with_header(
+                            "Content-Type: application/json"
+                                .parse::<tiny_http::Header>()
+                                .unwrap(),
+                        );
+                    let _ = request.respond(response);
+                }
+            })
+        };
+
+        Self {
+            server,
+            requests,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.server.server_addr().to_ip().unwrap())
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn header(request: &tiny_http::Request, name: &'static str) -> String {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.to_string())
+        .unwrap_or_default()
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,14 @@
+pub mod admin;
+pub mod images;
+pub mod publish;
+pub mod sync;
+pub mod token;
+
+#[cfg(test)]
+pub(crate) mod mock;
+
+pub use admin::*;
+pub use images::*;
+pub use publish::*;
+pub use sync::*;
+pub use token::*;
@@ -0,0 +1,359 @@
+use crate::api::{AdminClient, ApiError};
+use crate::models::ghost::{GhostImport, Post};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+
+/// Pushes the posts of a [`GhostImport`] to a live site over the Admin API.
+///
+/// Tags are looked up by slug and created when missing. Ghost does not allow
+/// staff users to be created over the API, so authors are matched to existing
+/// staff by email, then slug; posts whose authors are all unknown fall back
+/// to the site owner and the names are reported.
+pub struct Publisher<'a> {
+    client: &'a AdminClient,
+}
+
+#[derive(Debug, Clone)]
+pub struct PublishedPost {
+    pub slug: String,
+    pub id: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct PublishReport {
+    pub published: Vec<PublishedPost>,
+    /// Slug of each post that could not be created, with the reason
+    pub failed: Vec<(String, String)>,
+    pub tags_created: Vec<String>,
+    pub unknown_authors: Vec<String>,
+}
+
+/// Remote ids for the tags and users of an import, keyed by local id.
+#[derive(Debug, Default)]
+pub struct RemoteRefs {
+    tags: HashMap<i32, String>,
+    users: HashMap<i32, String>,
+}
+
+impl<'a> Publisher<'a> {
+    pub fn new(client: &'a AdminClient) -> Self {
+        Self { client }
+    }
+
+    pub fn publish(&self, import: &GhostImport) -> Result<PublishReport> {
+        let mut report = PublishReport::default();
+        let refs = self.resolve_relations(import, &mut report)?;
+
+        for post in &import.data.posts {
+            let payload = refs.post_payload(import, post);
+            match self
+                .client
+                .create_post(resource(post), &payload, is_html_only(post))
+            {
+                Ok(created) => report.published.push(PublishedPost {
+                    slug: created["slug"].as_str().unwrap_or(&post.slug).to_string(),
+                    id: created["id"].as_str().unwrap_or_default().to_string(),
+                    url: created["url"].as_str().map(str::to_string),
+                }),
+                Err(error) => report.failed.push((post.slug.clone(), error.to_string())),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Makes sure every tag the posts use exists on the site and finds the
+    /// staff users matching the import's authors.
+    pub fn resolve_relations(
+        &self,
+        import: &GhostImport,
+        report: &mut PublishReport,
+    ) -> Result<RemoteRefs> {
+        let mut refs = RemoteRefs::default();
+
+        let used_tags: BTreeSet<i32> = import.data.posts_tags.iter().map(|pt| pt.tag_id).collect();
+        for tag in import
+            .data
+            .tags
+            .iter()
+            .filter(|t| used_tags.contains(&t.id))
+        {
+            let remote = match self.client.find_tag(&tag.slug)? {
+                Some(remote) => remote,
+                None => {
+                    report.tags_created.push(tag.name.clone());
+                    self.client
+                        .create_tag(tag)
+                        .with_context(|| format!("Failed to create tag {:?}", tag.name))?
+                }
+            };
+            refs.tags.insert(tag.id, remote_id(&remote)?);
+        }
+
+        let used_users: BTreeSet<i32> = import
+            .data
+            .posts_authors
+            .iter()
+            .map(|pa| pa.author_id)
+            .collect();
+        for user in import
+            .data
+            .users
+            .iter()
+            .filter(|u| used_users.contains(&u.id))
+        {
+            match self.client.find_user(&user.slug, &user.email)? {
+                Some(remote) => {
+                    refs.users.insert(user.id, remote_id(&remote)?);
+                }
+                None => report.unknown_authors.push(user.name.clone()),
+            }
+        }
+
+        Ok(refs)
+    }
+}
+
+impl RemoteRefs {
+    /// Builds the Admin API representation of a post, with its tags and
+    /// authors pointing at the site's ids.
+    pub fn post_payload(&self, import: &GhostImport, post: &Post) -> Value {
+        let mut body = json!({
+            "title": post.title,
+            "slug": post.slug,
+            "status": post.status,
+            "visibility": post.visibility,
+            "featured": post.featured == Some(1),
+        });
+
+        if let Some(lexical) = &post.lexical {
+            body["lexical"] = json!(lexical);
+        } else if let Some(mobiledoc) = &post.mobiledoc {
+            body["mobiledoc"] = json!(mobiledoc);
+        } else if let Some(html) = &post.html {
+            body["html"] = json!(html);
+        }
+
+        for (field, value) in [
+            ("feature_image", &post.feature_image),
+            ("custom_excerpt", &post.custom_excerpt),
+            ("meta_title", &post.meta_title),
+            ("meta_description", &post.meta_description),
+            ("canonical_url", &post.canonical_url),
+        ] {
+            if let Some(value) = value {
+                body[field] = json!(value);
+            }
+        }
+        // The Admin API takes the posts_meta fields on the post itself
+        if let Some(meta) = import.data.posts_meta.iter().find(|m| m.post_id == post.id) {
+            for (field, value) in [
+                ("meta_title", &meta.meta_title),
+                ("meta_description", &meta.meta_description),
+                ("og_image", &meta.og_image),
+                ("og_title", &meta.og_title),
+                ("og_description", &meta.og_description),
+                ("twitter_image", &meta.twitter_image),
+                ("twitter_title", &meta.twitter_title),
+                ("twitter_description", &meta.twitter_description),
+                ("email_subject", &meta.email_subject),
+                ("feature_image_alt", &meta.feature_image_alt),
+                ("feature_image_caption", &meta.feature_image_caption),
+            ] {
+                if let Some(value) = value {
+                    body[field] = json!(value);
+                }
+            }
+        }
+        if let Some(published_at) = post.published_at {
+            body["published_at"] = json!(published_at);
+        }
+
+        // Ghost makes the first tag and author the primary ones
+        let mut tags: Vec<_> = import
+            .data
+            .posts_tags
+            .iter()
+            .filter(|pt| pt.post_id == post.id)
+            .collect();
+        tags.sort_by_key(|pt| pt.sort_order);
+        let tags: Vec<Value> = tags
+            .iter()
+            .filter_map(|pt| self.tags.get(&pt.tag_id))
+            .map(|id| json!({ "id": id }))
+            .collect();
+        body["tags"] = json!(tags);
+
+        let mut authors: Vec<_> = import
+            .data
+            .posts_authors
+            .iter()
+            .filter(|pa| pa.post_id == post.id)
+            .collect();
+        authors.sort_by_key(|pa| pa.sort_order);
+        let authors: Vec<Value> = authors
+            .iter()
+            .filter_map(|pa| self.users.get(&pa.author_id))
+            .map(|id| json!({ "id": id }))
+            .collect();
+        if !authors.is_empty() {
+            body["authors"] = json!(authors);
+        }
+
+        // Tiers are matched by slug, as the site's tiers have their own ids
+        let mut tiers: Vec<_> = import
+            .data
+            .posts_products
+            .iter()
+            .filter(|pp| pp.post_id == post.id)
+            .collect();
+        tiers.sort_by_key(|pp| pp.sort_order);
+        let tiers: Vec<Value> = tiers
+            .iter()
+            .filter_map(|pp| import.data.products.iter().find(|p| p.id == pp.product_id))
+            .map(|product| json!({ "slug": product.slug }))
+            .collect();
+        if !tiers.is_empty() {
+            body["tiers"] = json!(tiers);
+        }
+
+        body
+    }
+}
+
+/// Admin API collection a post belongs to.
+pub fn resource(post: &Post) -> &'static str {
+    if post.r#type == "page" {
+        "pages"
+    } else {
+        "posts"
+    }
+}
+
+/// Posts without an editor document need Ghost to convert their HTML.
+pub fn is_html_only(post: &Post) -> bool {
+    post.lexical.is_none() && post.mobiledoc.is_none()
+}
+
+fn remote_id(resource: &Value) -> Result<String, ApiError> {
+    resource["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| ApiError::Transport("response is missing an id".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockServer;
+    use crate::api::{AdminApiKey, RetryPolicy};
+    use crate::models::{Frontmatter, ProcessedMarkdown};
+    use crate::processors::GhostExporter;
+    use std::time::Duration;
+
+    fn import() -> GhostImport {
+        let processed = ProcessedMarkdown {
+            frontmatter: Frontmatter {
+                title: Some("Hello".to_string()),
+                tags: Some(vec!["Rust".to_string(), "New Tag".to_string()]),
+                authors: Some(vec!["Jane".to_string(), "Ghostwriter".to_string()]),
+                ..Default::default()
+            },
+            content: "Hi".to_string(),
+            html_content: "<p>Hi</p>".to_string(),
+            file_path: "hello.md".to_string(),
+            ..Default::default()
+        };
+        GhostExporter::create_export(vec![processed], None, vec![]).unwrap()
+    }
+
+    #[test]
+    fn test_publish() {
+        let server = MockServer::start(|request| {
+            match (request.method.as_str(), request.url.as_str()) {
+                ("GET", "/ghost/api/admin/tags/slug/rust/") => {
+                    (200, json!({ "tags": [{ "id": "tag-rust" }] }))
+                }
+                ("GET", url) if url.starts_with("/ghost/api/admin/tags/") => (404, json!({})),
+                ("POST", "/ghost/api/admin/tags/") => {
+                    (201, json!({ "tags": [{ "id": "tag-new" }] }))
+                }
+                ("GET", "/ghost/api/admin/users/slug/jane/") => {
+                    (200, json!({ "users": [{ "id": "user-jane" }] }))
+                }
+                ("GET", _) => (404, json!({})),
+                ("POST", "/ghost/api/admin/posts/?source=html") => (
+                    201,
+                    json!({ "posts": [{ "id": "post-1", "slug": "hello", "url": "https://site/hello/" }] }),
+                ),
+                _ => (500, json!({})),
+            }
+        });
+        let client = AdminClient::new(&server.url(), AdminApiKey::parse("k:00").unwrap())
+            .with_retry_policy(RetryPolicy {
+                max_retries: 0,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(1),
+            });
+
+        let report = Publisher::new(&client).publish(&import()).unwrap();
+
+        assert_eq!(report.published.len(), 1);
+        assert_eq!(report.published[0].id, "post-1");
+        assert_eq!(report.tags_created, vec!["New Tag".to_string()]);
+        assert_eq!(report.unknown_authors, vec!["Ghostwriter".to_string()]);
+        assert!(report.failed.is_empty());
+
+        let requests = server.requests();
+        let created_tag = requests
+            .iter()
+            .find(|r| r.url == "/ghost/api/admin/tags/")
+            .unwrap();
+        assert_eq!(created_tag.body["tags"][0]["slug"], "new-tag");
+
+        let post = &requests.last().unwrap().body["posts"][0];
+        assert_eq!(post["title"], "Hello");
+        assert_eq!(post["html"], "<p>Hi</p>");
+        assert_eq!(post["og_title"], "Hello");
+        assert_eq!(
+            post["tags"],
+            json!([{ "id": "tag-rust" }, { "id": "tag-new" }])
+        );
+        assert_eq!(post["authors"], json!([{ "id": "user-jane" }]));
+    }
+
+    #[test]
+    fn test_payload_follows_sort_order() {
+        let mut import = import();
+        import.data.posts_tags.reverse();
+        import.data.posts_authors.reverse();
+        let refs = RemoteRefs {
+            tags: import.data.tags.iter().map(|t| (t.id, t.slug.clone())).collect(),
+            users: import.data.users.iter().map(|u| (u.id, u.slug.clone())).collect(),
+        };
+
+        let payload = refs.post_payload(&import, &import.data.posts[0]);
+        assert_eq!(payload["tags"], json!([{ "id": "rust" }, { "id": "new-tag" }]));
+        assert_eq!(payload["authors"], json!([{ "id": "jane" }, { "id": "ghostwriter" }]));
+    }
+
+    #[test]
+    fn test_payload_names_tiers_by_slug() {
+        let processed = ProcessedMarkdown {
+            frontmatter: serde_yaml::from_str("title: Gold\ntiers: [Gold Plan, Silver]\n").unwrap(),
+            file_path: "gold.md".to_string(),
+            ..Default::default()
+        };
+        let import = GhostExporter::create_export(vec![processed], None, vec![]).unwrap();
+        let post = &import.data.posts[0];
+
+        let payload = RemoteRefs::default().post_payload(&import, post);
+        assert_eq!(payload["visibility"], "tiers");
+        assert_eq!(
+            payload["tiers"],
+            json!([{ "slug": "gold-plan" }, { "slug": "silver" }])
+        );
+    }
+}
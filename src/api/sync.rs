@@ -0,0 +1,577 @@
+use crate::api::{is_html_only, resource, AdminClient, ApiError, PublishReport, Publisher};
+use crate::models::ghost::{GhostImport, Post};
+use crate::models::ProcessedMarkdown;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// What happens to synced posts whose source file has been removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PruneMode {
+    /// Leave them on the site and only report them
+    #[default]
+    Keep,
+    /// Turn them back into drafts
+    Unpublish,
+    /// Delete them from the site
+    Delete,
+}
+
+/// What the state file remembers about one synced source file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncEntry {
+    pub post_id: String,
+    /// `posts` or `pages`
+    pub resource: String,
+    pub slug: String,
+    /// SHA-256 of the post as last sent to Ghost
+    pub content_hash: String,
+    /// Ghost's `updated_at` after the last write, needed for the next edit
+    pub updated_at: String,
+    /// Publication date sent for posts whose frontmatter has none, so it
+    /// stays put across runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<DateTime<Utc>>,
+    /// Set once the post was unpublished because its source disappeared
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pruned: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateFile {
+    site: String,
+    /// Source path relative to the input directory -> entry
+    posts: BTreeMap<String, SyncEntry>,
+}
+
+/// Maps source files to the Ghost posts they were synced to.
+#[derive(Debug)]
+pub struct SyncState {
+    path: PathBuf,
+    state: StateFile,
+}
+
+impl SyncState {
+    /// Loads the state stored at `path`, starting empty when it does not
+    /// exist. A state file written for another site is refused.
+    pub fn load(path: &Path, site: &str) -> Result<Self> {
+        let state = if path.exists() {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read sync state {:?}", path))?;
+            let state: StateFile = serde_json::from_str(&json)
+                .with_context(|| format!("Sync state {:?} is not valid", path))?;
+            if state.site != site {
+                anyhow::bail!(
+                    "Sync state {:?} belongs to {}, not {}",
+                    path,
+                    state.site,
+                    site
+                );
+            }
+            state
+        } else {
+            StateFile {
+                site: site.to_string(),
+                posts: BTreeMap::new(),
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            state,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.state)?;
+        std::fs::write(&self.path, json)
+            .with_context(|| format!("Failed to write sync state {:?}", self.path))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SyncEntry> {
+        self.state.posts.get(key)
+    }
+
+    pub fn entries(&self) -> &BTreeMap<String, SyncEntry> {
+        &self.state.posts
+    }
+
+    /// Gives posts without a frontmatter date the date they were first synced
+    /// with (or `now` for new ones), so undated posts neither move nor look
+    /// changed on every run.
+    pub fn pin_dates(&self, posts: &mut [ProcessedMarkdown], root: &Path, now: DateTime<Utc>) {
+        for post in posts.iter_mut().filter(|p| p.frontmatter.date.is_none()) {
+            let date = self
+                .get(&state_key(root, &post.file_path))
+                .and_then(|entry| entry.published_at)
+                .unwrap_or(now);
+            post.frontmatter.date = Some(date.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Posts edited in Ghost since the last sync, left as they are
+    pub conflicts: Vec<String>,
+    /// Posts edited in Ghost since the last sync, overwritten with `force`
+    pub overwritten: Vec<String>,
+    pub pruned: Vec<String>,
+    /// Posts whose source is gone but were kept because pruning is off
+    pub orphaned: Vec<String>,
+    pub failed: Vec<(String, String)>,
+    pub tags_created: Vec<String>,
+    pub unknown_authors: Vec<String>,
+}
+
+/// Brings a site in line with a directory of Markdown files, using a
+/// [`SyncState`] to tell new, changed and removed files apart.
+pub struct Syncer<'a> {
+    client: &'a AdminClient,
+    root: PathBuf,
+    force: bool,
+}
+
+enum Outcome {
+    Created,
+    Updated,
+}
+
+impl<'a> Syncer<'a> {
+    /// `root` is the input directory state keys are relative to.
+    pub fn new(client: &'a AdminClient, root: &Path) -> Self {
+        Self {
+            client,
+            root: root.to_path_buf(),
+            force: false,
+        }
+    }
+
+    /// Overwrites posts edited in Ghost since the last sync instead of
+    /// leaving them alone.
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    pub fn sync(
+        &self,
+        import: &GhostImport,
+        state: &mut SyncState,
+        prune: PruneMode,
+    ) -> Result<SyncReport> {
+        let mut relations = PublishReport::default();
+        let refs = Publisher::new(self.client).resolve_relations(import, &mut relations)?;
+        let mut report = SyncReport {
+            tags_created: relations.tags_created,
+            unknown_authors: relations.unknown_authors,
+            ..Default::default()
+        };
+
+        let mut seen = BTreeSet::new();
+        for post in &import.data.posts {
+            let Some(source_path) = &post.source_path else {
+                continue;
+            };
+            let key = state_key(&self.root, source_path);
+            seen.insert(key.clone());
+
+            let payload = refs.post_payload(import, post);
+            let hash = hex::encode(Sha256::digest(payload.to_string()));
+
+            let result = match state.get(&key) {
+                Some(entry) if entry.content_hash == hash && !entry.pruned => {
+                    report.unchanged.push(key);
+                    continue;
+                }
+                Some(entry) => match self.update(entry, post, &payload, &key, &mut report) {
+                    Ok(Some(result)) => Ok(result),
+                    Ok(None) => continue,
+                    Err(error) => Err(error),
+                },
+                None => self
+                    .client
+                    .create_post(resource(post), &payload, is_html_only(post))
+                    .map(|remote| (remote, Outcome::Created)),
+            };
+
+            match result {
+                Ok((remote, outcome)) => {
+                    let entry = SyncEntry {
+                        post_id: remote["id"].as_str().unwrap_or_default().to_string(),
+                        resource: resource(post).to_string(),
+                        slug: remote["slug"].as_str().unwrap_or(&post.slug).to_string(),
+                        content_hash: hash,
+                        updated_at: remote["updated_at"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        published_at: post.published_at,
+                        pruned: false,
+                    };
+                    state.state.posts.insert(key.clone(), entry);
+                    state.save()?;
+                    match outcome {
+                        Outcome::Created => report.created.push(key),
+                        Outcome::Updated => report.updated.push(key),
+                    }
+                }
+                Err(error) => report.failed.push((key, error.to_string())),
+            }
+        }
+
+        // A file that is still there but did not load, or was excluded, is
+        // not gone.
+        let gone: Vec<String> = state
+            .entries()
+            .iter()
+            .filter(|(key, entry)| !seen.contains(*key) && !entry.pruned)
+            .filter(|(key, _)| !self.root.join(key).exists())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in gone {
+            if prune == PruneMode::Keep {
+                report.orphaned.push(key);
+                continue;
+            }
+            match self.prune(&key, state, prune) {
+                Ok(()) => report.pruned.push(key),
+                Err(error) => report.failed.push((key, error.to_string())),
+            }
+            state.save()?;
+        }
+
+        Ok(report)
+    }
+
+    /// Sends an edit with the `updated_at` from the last sync. When the post
+    /// was changed in Ghost meanwhile it is reported as a conflict and left
+    /// alone, or with `force` the edit is sent again against the current
+    /// version. A post deleted in Ghost is created again.
+    fn update(
+        &self,
+        entry: &SyncEntry,
+        post: &Post,
+        payload: &Value,
+        key: &str,
+        report: &mut SyncReport,
+    ) -> Result<Option<(Value, Outcome)>, ApiError> {
+        let source_html = is_html_only(post);
+        let mut body = payload.clone();
+        body["updated_at"] = json!(entry.updated_at);
+
+        let result = match self
+            .client
+            .update_post(&entry.resource, &entry.post_id, &body, source_html)
+        {
+            Err(error) if error.status() == Some(409) => {
+                let Some(current) = self.client.get_post(&entry.resource, &entry.post_id)? else {
+                    return self
+                        .client
+                        .create_post(resource(post), payload, source_html)
+                        .map(|remote| Some((remote, Outcome::Created)));
+                };
+                if !self.force {
+                    report.conflicts.push(key.to_string());
+                    return Ok(None);
+                }
+                report.overwritten.push(key.to_string());
+                body["updated_at"] = current["updated_at"].clone();
+                self.client
+                    .update_post(&entry.resource, &entry.post_id, &body, source_html)
+            }
+            Err(error) if error.status() == Some(404) => {
+                return self
+                    .client
+                    .create_post(resource(post), payload, source_html)
+                    .map(|remote| Some((remote, Outcome::Created)));
+            }
+            result => result,
+        };
+        result.map(|remote| Some((remote, Outcome::Updated)))
+    }
+
+    fn prune(&self, key: &str, state: &mut SyncState, prune: PruneMode) -> Result<(), ApiError> {
+        let Some(entry) = state.state.posts.get(key).cloned() else {
+            return Ok(());
+        };
+        let current = self.client.get_post(&entry.resource, &entry.post_id)?;
+
+        match (prune, current) {
+            (PruneMode::Unpublish, Some(current)) => {
+                let body = json!({ "status": "draft", "updated_at": current["updated_at"] });
+                let remote =
+                    self.client
+                        .update_post(&entry.resource, &entry.post_id, &body, false)?;
+                let entry = state.state.posts.get_mut(key).expect("entry exists");
+                entry.updated_at = remote["updated_at"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                // Forces an update should the source file come back.
+                entry.content_hash.clear();
+                entry.pruned = true;
+            }
+            (PruneMode::Delete, Some(_)) => {
+                self.client.delete_post(&entry.resource, &entry.post_id)?;
+                state.state.posts.remove(key);
+            }
+            // Already gone from the site
+            _ => {
+                state.state.posts.remove(key);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// State key for a source file: its path relative to the input directory,
+/// with forward slashes.
+pub fn state_key(root: &Path, source_path: &str) -> String {
+    let path = Path::new(source_path);
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockServer;
+    use crate::api::{AdminApiKey, RetryPolicy};
+    use crate::models::Frontmatter;
+    use crate::processors::GhostExporter;
+    use std::time::Duration;
+
+    fn import(root: &Path, posts: &[(&str, &str)]) -> GhostImport {
+        let processed = posts
+            .iter()
+            .map(|(file, content)| ProcessedMarkdown {
+                frontmatter: Frontmatter {
+                    title: Some(file.to_string()),
+                    date: Some("2024-01-01".to_string()),
+                    ..Default::default()
+                },
+                content: content.to_string(),
+                html_content: format!("<p>{}</p>", content),
+                file_path: root.join(file).to_string_lossy().to_string(),
+                ..Default::default()
+            })
+            .collect();
+        GhostExporter::create_export(processed, None, vec![]).unwrap()
+    }
+
+    fn client(server: &MockServer) -> AdminClient {
+        AdminClient::new(&server.url(), AdminApiKey::parse("k:00").unwrap()).with_retry_policy(
+            RetryPolicy {
+                max_retries: 0,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(1),
+            },
+        )
+    }
+
+    #[test]
+    fn test_sync_creates_updates_and_prunes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let server = MockServer::start(|request| {
+            let id = format!(
+                "id-{}",
+                request.body["posts"][0]["title"].as_str().unwrap_or("")
+            );
+            let updated_at = format!("2024-01-0{}T00:00:00.000Z", request.index % 9 + 1);
+            match (request.method.as_str(), request.url.as_str()) {
+                // b.md was edited in Ghost since the last sync
+                ("PUT", url)
+                    if url.contains("/id-b.md/")
+                        && request.body["posts"][0]["updated_at"] != "stale" =>
+                {
+                    (
+                        200,
+                        json!({ "posts": [{ "id": "id-b.md", "updated_at": updated_at }] }),
+                    )
+                }
+                ("PUT", url) if url.contains("/id-b.md/") => (
+                    409,
+                    json!({ "errors": [{ "type": "UpdateCollisionError", "message": "Saving failed!" }] }),
+                ),
+                ("GET", url) if url.contains("/users/") => (404, json!({})),
+                ("GET", _) => (
+                    200,
+                    json!({ "posts": [{ "id": "x", "updated_at": "current" }] }),
+                ),
+                ("DELETE", _) => (204, Value::Null),
+                _ => (
+                    201,
+                    json!({ "posts": [{ "id": id, "updated_at": updated_at }] }),
+                ),
+            }
+        });
+        let client = client(&server);
+        let syncer = Syncer::new(&client, root);
+        let mut state = SyncState::load(&root.join("state.json"), client.site_url()).unwrap();
+
+        let first = import(root, &[("a.md", "A"), ("b.md", "B"), ("c.md", "C")]);
+        let report = syncer.sync(&first, &mut state, PruneMode::Delete).unwrap();
+        assert_eq!(report.created, vec!["a.md", "b.md", "c.md"]);
+        assert_eq!(state.get("b.md").unwrap().post_id, "id-b.md");
+
+        // Nothing changed, so no post is written
+        let writes = |server: &MockServer| {
+            server
+                .requests()
+                .iter()
+                .filter(|r| r.method != "GET")
+                .count()
+        };
+        let before = writes(&server);
+        let report = syncer.sync(&first, &mut state, PruneMode::Delete).unwrap();
+        assert_eq!(report.unchanged.len(), 3);
+        assert_eq!(writes(&server), before);
+
+        // b.md changed remotely and locally, c.md is gone
+        state.state.posts.get_mut("b.md").unwrap().updated_at = "stale".to_string();
+        let second = import(root, &[("a.md", "A"), ("b.md", "B2")]);
+        let report = syncer.sync(&second, &mut state, PruneMode::Delete).unwrap();
+        assert_eq!(report.unchanged, vec!["a.md"]);
+        assert!(report.updated.is_empty());
+        assert_eq!(report.conflicts, vec!["b.md"]);
+        assert_eq!(report.pruned, vec!["c.md"]);
+        assert!(report.failed.is_empty());
+        assert!(state.get("c.md").is_none());
+        assert_eq!(state.get("b.md").unwrap().updated_at, "stale");
+        assert_eq!(server.requests().iter().filter(|r| r.method == "PUT").count(), 1);
+
+        // Only --force overwrites the edits made in Ghost
+        let forced = Syncer::new(&client, root).with_force(true);
+        let report = forced.sync(&second, &mut state, PruneMode::Delete).unwrap();
+        assert_eq!(report.updated, vec!["b.md"]);
+        assert_eq!(report.overwritten, vec!["b.md"]);
+        assert!(report.conflicts.is_empty());
+
+        let requests = server.requests();
+        let retried = requests.iter().rfind(|r| r.method == "PUT").unwrap();
+        assert_eq!(retried.body["posts"][0]["updated_at"], "current");
+        assert_eq!(retried.body["posts"][0]["html"], "<p>B2</p>");
+        assert!(requests.iter().any(|r| r.method == "DELETE"));
+
+        let reloaded = SyncState::load(&root.join("state.json"), client.site_url()).unwrap();
+        assert_eq!(reloaded.entries().len(), 2);
+        assert!(SyncState::load(&root.join("state.json"), "https://other.site").is_err());
+    }
+
+    #[test]
+    fn test_posts_deleted_in_ghost_are_created_again() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let server = MockServer::start(|request| match request.method.as_str() {
+            "PUT" if request.body["posts"][0]["updated_at"] == "stale" => (409, json!({})),
+            "PUT" | "GET" => (404, json!({})),
+            _ => (
+                201,
+                json!({ "posts": [{ "id": format!("id-{}", request.index), "updated_at": "t" }] }),
+            ),
+        });
+        let client = client(&server);
+        let syncer = Syncer::new(&client, root);
+        let mut state = SyncState::load(&root.join("state.json"), client.site_url()).unwrap();
+        syncer
+            .sync(&import(root, &[("a.md", "A"), ("b.md", "B")]), &mut state, PruneMode::Keep)
+            .unwrap();
+        let old_ids: Vec<String> = ["a.md", "b.md"]
+            .iter()
+            .map(|key| state.get(key).unwrap().post_id.clone())
+            .collect();
+
+        // a.md is gone from Ghost; so is b.md, found out through a conflict
+        state.state.posts.get_mut("b.md").unwrap().updated_at = "stale".to_string();
+        let report = syncer
+            .sync(&import(root, &[("a.md", "A2"), ("b.md", "B2")]), &mut state, PruneMode::Keep)
+            .unwrap();
+        assert_eq!(report.created, vec!["a.md", "b.md"]);
+        assert!(report.updated.is_empty());
+        assert!(report.conflicts.is_empty());
+        assert_ne!(state.get("a.md").unwrap().post_id, old_ids[0]);
+        assert_ne!(state.get("b.md").unwrap().post_id, old_ids[1]);
+    }
+
+    #[test]
+    fn test_unpublish_and_pin_dates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let server = MockServer::start(|request| {
+            if request.url.contains("/users/") {
+                return (404, json!({}));
+            }
+            (
+                200,
+                json!({ "posts": [{ "id": format!("id-{}", request.index), "updated_at": "t" }] }),
+            )
+        });
+        let client = client(&server);
+        let syncer = Syncer::new(&client, root);
+        let mut state = SyncState::load(&root.join("state.json"), client.site_url()).unwrap();
+
+        syncer
+            .sync(
+                &import(root, &[("a.md", "A")]),
+                &mut state,
+                PruneMode::Unpublish,
+            )
+            .unwrap();
+        // A source that is still on disk but did not load is not gone
+        std::fs::write(root.join("a.md"), "---\ntitle: [broken\n---\n").unwrap();
+        let report = syncer
+            .sync(&import(root, &[]), &mut state, PruneMode::Unpublish)
+            .unwrap();
+        assert!(report.pruned.is_empty());
+        assert!(report.orphaned.is_empty());
+
+        std::fs::remove_file(root.join("a.md")).unwrap();
+        let report = syncer
+            .sync(&import(root, &[]), &mut state, PruneMode::Unpublish)
+            .unwrap();
+        assert_eq!(report.pruned, vec!["a.md"]);
+        let last = server.requests().last().unwrap().clone();
+        assert_eq!(last.method, "PUT");
+        assert_eq!(last.body["posts"][0]["status"], "draft");
+        assert!(state.get("a.md").unwrap().pruned);
+
+        // Pruned posts are not unpublished twice
+        let report = syncer
+            .sync(&import(root, &[]), &mut state, PruneMode::Unpublish)
+            .unwrap();
+        assert!(report.pruned.is_empty());
+
+        let mut posts = vec![
+            ProcessedMarkdown {
+                file_path: root.join("a.md").to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            ProcessedMarkdown {
+                file_path: root.join("new.md").to_string_lossy().to_string(),
+                ..Default::default()
+            },
+        ];
+        let now = "2024-05-01T12:30:00Z".parse().unwrap();
+        state.pin_dates(&mut posts, root, now);
+        assert_eq!(
+            posts[0].frontmatter.date.as_deref(),
+            Some("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            posts[1].frontmatter.date.as_deref(),
+            Some("2024-05-01T12:30:00Z")
+        );
+    }
+}
@@ -1,9 +1,11 @@
+pub mod api;
 pub mod models;
 pub mod processors;
 pub mod utils;
 
+pub use api::admin as admin_api;
 pub use models::ghost as ghost_models;
 pub use models::markdown as markdown_models;
 pub use processors::markdown as markdown_processor;
 pub use processors::ghost_export as ghost_exporter;
-pub use utils::file_ops as file_operations;
\ No newline at end of file
+pub use utils::file_ops as file_operations;
@@ -1,29 +1,53 @@
-use clap::{Parser, ValueEnum};
-use std::path::PathBuf;
-
-mod models;
-mod processors;
-mod utils;
-
-use processors::{GhostExporter, MarkdownProcessor};
-use utils::FileOps;
+use anyhow::Context;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use ghost_markdown_importer::api::{
+    replace_image_urls, AdminApiKey, AdminClient, ImageUploader, PruneMode, Publisher,
+    RetryPolicy, SyncState, Syncer, UploadCache,
+};
+use ghost_markdown_importer::models;
+use ghost_markdown_importer::models::ghost::{
+    ContentFormat, GhostImport, GhostVersion, MobiledocMode, PostVisibility, StaffRole,
+};
+use ghost_markdown_importer::models::ProcessedMarkdown;
+use ghost_markdown_importer::processors::{
+    AssetManifest, AssetResolver, AuthorRegistry, CategoryHierarchy, ExcerptEllipsis, ExportOptions, GhostExporter,
+    GhostReader, ImportMerger, MarkdownExportOptions, MarkdownExporter, MarkdownProcessor,
+    MergeReport, NameMerge, PageRules, PrimaryTag, SlugConflict, SlugKind, SlugStyle, TagRegistry,
+    ValidationReport, Validator, DEFAULT_PAGE_GLOBS, DEFAULT_PAGE_LAYOUTS,
+};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "gmi")]
 #[command(about = "Convert Markdown files to Ghost CMS import format", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Publish posts straight to a Ghost site through the Admin API
+    Publish(PublishArgs),
+    /// Create, update and prune posts so a site matches the input directory
+    Sync(SyncArgs),
+    /// Turn a Ghost export back into Markdown files with frontmatter
+    ExportMd(ExportMdArgs),
+    /// Check an import file for problems that make Ghost reject it
+    Validate(ValidateArgs),
+}
+
+/// Where posts come from and how they are turned into Ghost content.
+#[derive(Args)]
+struct SourceArgs {
     /// Input directory containing markdown files
     #[arg(value_name = "INPUT")]
     input: PathBuf,
 
-    /// Output file path (JSON or ZIP)
-    #[arg(short, long, value_name = "OUTPUT")]
-    output: Option<PathBuf>,
-
-    /// Export format
-    #[arg(short, long, value_enum, default_value_t = Format::Json)]
-    format: Format,
-
     /// Process directories recursively
     #[arg(short, long)]
     recursive: bool,
@@ -36,81 +60,349 @@ struct Cli {
     #[arg(long, value_delimiter = ',')]
     default_tags: Vec<String>,
 
+    /// Internal tags to add to every post, e.g. --internal-tag imported
+    /// adds #imported
+    #[arg(long = "internal-tag", value_name = "TAG", value_delimiter = ',')]
+    internal_tags: Vec<String>,
+
     /// Exclude files matching these patterns
     #[arg(long, value_delimiter = ',')]
     exclude: Vec<String>,
 
+    /// Storage format for post content
+    #[arg(long, value_enum, default_value_t = ContentFormat::Html)]
+    content_format: ContentFormat,
+
+    /// Mobiledoc layout when --content-format is mobiledoc
+    #[arg(long, value_enum, default_value_t = MobiledocMode::MarkdownCard)]
+    mobiledoc_mode: MobiledocMode,
+
+    /// Ghost major version whose import schema to target
+    /// [default: 4 for mobiledoc content, otherwise 5]
+    #[arg(long, value_enum)]
+    ghost_version: Option<GhostVersion>,
+
+    /// Author profiles: an authors.yaml file or a directory of Markdown files
+    /// [default: authors.yaml, authors/, data/authors/ or ../authors/ in INPUT]
+    #[arg(long, value_name = "PATH")]
+    authors: Option<PathBuf>,
+
+    /// Tag descriptions, images, colours and synonyms: a tags.yaml file or a
+    /// taxonomy directory of <term>/_index.md pages
+    /// [default: tags.yaml, tags/, content/tags/ or ../tags/ in INPUT]
+    #[arg(long, value_name = "PATH")]
+    tags: Option<PathBuf>,
+
+    /// Frontmatter type or layout values that make a file a page
+    #[arg(
+        long,
+        value_name = "LAYOUTS",
+        value_delimiter = ',',
+        default_values = DEFAULT_PAGE_LAYOUTS
+    )]
+    page_layouts: Vec<String>,
+
+    /// Paths under INPUT whose files are pages
+    #[arg(
+        long,
+        value_name = "GLOBS",
+        value_delimiter = ',',
+        default_values = DEFAULT_PAGE_GLOBS
+    )]
+    page_globs: Vec<String>,
+
+    /// Publish posts dated in the future right away instead of scheduling them
+    #[arg(long)]
+    publish_future: bool,
+
+    /// Who can read posts whose frontmatter sets no visibility
+    #[arg(long, value_enum, default_value_t = PostVisibility::Public)]
+    default_visibility: PostVisibility,
+
+    /// What ends excerpts cut to Ghost's 300-character limit
+    #[arg(long, value_enum, default_value_t = ExcerptEllipsis::Unicode)]
+    excerpt_ellipsis: ExcerptEllipsis,
+
+    /// How category paths such as a/b become tags
+    #[arg(long, value_enum, default_value_t = CategoryHierarchy::Split)]
+    category_hierarchy: CategoryHierarchy,
+
+    /// Whether the first category or the first tag is a post's primary tag
+    #[arg(long, value_enum, default_value_t = PrimaryTag::Category)]
+    primary_tag: PrimaryTag,
+
+    /// Staff role for an author, e.g. --role "Jane Doe=editor" (repeatable)
+    #[arg(long = "role", value_name = "AUTHOR=ROLE", value_parser = parse_role)]
+    roles: Vec<(String, StaffRole)>,
+
+    /// Staff role for authors without one
+    #[arg(long, value_enum, default_value_t = StaffRole::Author)]
+    default_role: StaffRole,
+
+    /// How non-ASCII titles, tags and author names become slugs
+    #[arg(long, value_enum, default_value_t = SlugStyle::Unicode)]
+    slug_style: SlugStyle,
+
+    /// Verbose output
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Output file path (JSON or ZIP)
+    #[arg(short, long, value_name = "OUTPUT")]
+    output: Option<PathBuf>,
+
+    /// Export format
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
     /// Include images in ZIP export
     #[arg(long)]
     include_images: bool,
 
+    /// Existing site export to merge the new posts into, reusing its tags
+    /// and users
+    #[arg(long, value_name = "EXPORT")]
+    merge_into: Option<PathBuf>,
+
+    /// What to do with new posts whose slug an existing post already uses
+    #[arg(long, value_enum, default_value_t = SlugConflict::Skip, requires = "merge_into")]
+    on_conflict: SlugConflict,
+
+    /// Truncate overlong fields, de-duplicate slugs and drop broken relations
+    #[arg(long)]
+    fix: bool,
+}
+
+#[derive(Args)]
+struct PublishArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Site URL, e.g. https://blog.example.com
+    #[arg(long, value_name = "URL")]
+    url: String,
+
+    /// Admin API key from a custom integration (id:secret)
+    #[arg(long, env = "GHOST_ADMIN_API_KEY", hide_env_values = true)]
+    admin_key: String,
+
+    /// Retries for rate-limited or failed requests
+    #[arg(long, default_value_t = 3)]
+    max_retries: u32,
+
+    /// File remembering uploaded images so re-runs skip them
+    /// [default: <INPUT>/.gmi-uploads.json]
+    #[arg(long, value_name = "PATH")]
+    upload_cache: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SyncArgs {
+    #[command(flatten)]
+    target: PublishArgs,
+
+    /// File mapping source files to their Ghost posts
+    /// [default: <INPUT>/.gmi-sync.json]
+    #[arg(long, value_name = "PATH")]
+    state: Option<PathBuf>,
+
+    /// What to do with posts whose source file was removed
+    #[arg(long, value_enum, default_value_t = PruneMode::Keep)]
+    prune: PruneMode,
+
+    /// Overwrite posts that were edited in Ghost since the last sync
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct ExportMdArgs {
+    /// Ghost export JSON file
+    #[arg(value_name = "EXPORT")]
+    export: PathBuf,
+
+    /// Directory to write the Markdown files to
+    #[arg(value_name = "OUTDIR")]
+    output_dir: PathBuf,
+
+    /// Ghost content directory to copy images from
+    #[arg(long, value_name = "DIR")]
+    content_dir: Option<PathBuf>,
+
+    /// Site URL to download images from when they are not found locally
+    #[arg(long, value_name = "URL")]
+    site_url: Option<String>,
+
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
 }
 
+#[derive(Args)]
+struct ValidateArgs {
+    /// Ghost import or export JSON file
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Fix what can be fixed automatically and save the result
+    #[arg(long)]
+    fix: bool,
+
+    /// Where to save the fixed import [default: overwrite FILE]
+    #[arg(short, long, value_name = "OUTPUT", requires = "fix")]
+    output: Option<PathBuf>,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Format {
     Json,
     Zip,
 }
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+impl SourceArgs {
+    fn export_options(&self) -> anyhow::Result<ExportOptions> {
+        let authors = match &self.authors {
+            Some(path) => AuthorRegistry::load(path)?,
+            None => AuthorRegistry::discover(&self.input)?.unwrap_or_default(),
+        };
+        let tags = match &self.tags {
+            Some(path) => TagRegistry::load(path)?,
+            None => TagRegistry::discover(&self.input)?.unwrap_or_default(),
+        };
+        Ok(ExportOptions {
+            default_author: self.author.clone(),
+            default_tags: self.default_tags.clone(),
+            content_format: self.content_format,
+            mobiledoc_mode: self.mobiledoc_mode,
+            ghost_version: self.ghost_version,
+            slug_style: self.slug_style,
+            category_hierarchy: self.category_hierarchy,
+            primary_tag: self.primary_tag,
+            internal_tags: self.internal_tags.clone(),
+            publish_future: self.publish_future,
+            default_visibility: self.default_visibility,
+            excerpt_ellipsis: self.excerpt_ellipsis,
+            pages: PageRules::new(&self.input, &self.page_layouts, &self.page_globs)?,
+            default_role: self.default_role,
+            roles: self
+                .roles
+                .iter()
+                .map(|(author, role)| {
+                    let key = authors.key_for(author).unwrap_or(author);
+                    (key.trim().to_lowercase(), *role)
+                })
+                .collect(),
+            authors,
+            tags,
+        })
+    }
+}
+
+fn parse_role(value: &str) -> Result<(String, StaffRole), String> {
+    let (author, role) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected AUTHOR=ROLE, got {:?}", value))?;
+    Ok((author.to_string(), StaffRole::try_from(role.to_string())?))
+}
 
-    if !cli.input.exists() {
-        anyhow::bail!("Input directory does not exist: {:?}", cli.input);
+fn main() -> anyhow::Result<()> {
+    // The conversion arguments are required when no subcommand is given, so
+    // only the half of the command line that was used can be extracted.
+    let matches = Cli::command().get_matches();
+    let command = matches
+        .subcommand()
+        .map(|_| Command::from_arg_matches(&matches))
+        .transpose()
+        .unwrap_or_else(|e| e.exit());
+
+    match command {
+        Some(Command::Publish(args)) => publish(args),
+        Some(Command::Sync(args)) => sync(args),
+        Some(Command::ExportMd(args)) => export_markdown(args),
+        Some(Command::Validate(args)) => validate(args),
+        None => convert(ConvertArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())),
     }
+}
 
-    let output_path = cli.output.unwrap_or_else(|| {
-        let extension = match cli.format {
-            Format::Json => "json",
-            Format::Zip => "zip",
-        };
-        std::env::current_dir()
-            .unwrap()
-            .join(format!("ghost-import.{}", extension))
-    });
+/// Reads and converts every Markdown file under the input directory, except
+/// the author and tag profiles in `options`. With `rewrite_images`, local
+/// image references are pointed at their `content/images` location and
+/// collected into the returned manifest.
+fn load_posts(
+    source: &SourceArgs,
+    options: &ExportOptions,
+    rewrite_images: bool,
+) -> anyhow::Result<(Vec<ProcessedMarkdown>, AssetManifest, Vec<PathBuf>)> {
+    if !source.input.exists() {
+        anyhow::bail!("Input directory does not exist: {:?}", source.input);
+    }
 
-    if cli.verbose {
-        println!("Searching for markdown files in: {:?}", cli.input);
+    if source.verbose {
+        println!("Searching for markdown files in: {:?}", source.input);
     }
 
-    let markdown_files = MarkdownProcessor::collect_markdown_files(&cli.input, 
-        cli.recursive
+    let markdown_files = MarkdownProcessor::collect_markdown_files(&source.input, 
+        source.recursive
     )?;
 
     if markdown_files.is_empty() {
-        anyhow::bail!("No markdown files found in: {:?}", cli.input);
+        anyhow::bail!("No markdown files found in: {:?}", source.input);
     }
 
-    if cli.verbose {
+    if source.verbose {
         println!("Found {} markdown files", markdown_files.len());
     }
 
+    let asset_resolver = AssetResolver::new(&source.input);
+    let mut asset_manifest = AssetManifest::new();
+
     let mut processed_posts = Vec::new();
+    let mut failed = Vec::new();
     for file in markdown_files {
-        if cli.verbose {
+        if source.verbose {
             println!("Processing: {:?}", file);
         }
 
         if let Some(file_name) = file.to_str() {
-            if cli.exclude.iter().any(|pattern| {
+            if source.exclude.iter().any(|pattern| {
                 file_name.contains(pattern)
             }) {
-                if cli.verbose {
+                if source.verbose {
                     println!("Skipping excluded file: {:?}", file);
                 }
                 continue;
             }
         }
 
+        if options.authors.is_source(&file) || options.tags.is_source(&file) {
+            if source.verbose {
+                println!("Skipping profile: {:?}", file);
+            }
+            continue;
+        }
+
         match MarkdownProcessor::process_file(&file) {
-            Ok(processed) => {
+            Ok(mut processed) => {
+                if rewrite_images {
+                    let assets = asset_resolver.rewrite_post(&mut processed);
+                    if source.verbose {
+                        for asset in &assets {
+                            println!("Rewrote image: {} -> {}", asset.original, asset.url);
+                        }
+                    }
+                    asset_manifest.add_post(&processed.file_path, assets);
+                }
                 processed_posts.push(processed);
             }
             Err(e) => {
                 eprintln!("Error processing {:?}: {}", file, e);
+                failed.push(file);
                 continue;
             }
         }
@@ -120,11 +412,63 @@ fn main() -> anyhow::Result<()> {
         anyhow::bail!("No posts could be processed successfully");
     }
 
-    let ghost_import = GhostExporter::create_export(
-        processed_posts,
-        cli.author.as_deref(),
-        cli.default_tags,
-    )?;
+    Ok((processed_posts, asset_manifest, failed))
+}
+
+/// Points local author and tag images at their `content/images` location
+/// and adds them to the manifest, next to the images of the posts.
+fn add_profile_images(options: &mut ExportOptions, input: &Path, manifest: &mut AssetManifest) {
+    let resolver = AssetResolver::new(input);
+    for (author, assets) in options.authors.rewrite_images(&resolver) {
+        manifest.add_post(&format!("author {}", author), assets);
+    }
+    for (tag, assets) in options.tags.rewrite_images(&resolver) {
+        manifest.add_post(&format!("tag {}", tag), assets);
+    }
+}
+
+fn convert(cli: ConvertArgs) -> anyhow::Result<()> {
+    let output_path = cli.output.clone().unwrap_or_else(|| {
+        let extension = match cli.format {
+            Format::Json => "json",
+            Format::Zip => "zip",
+        };
+        std::env::current_dir()
+            .unwrap()
+            .join(format!("ghost-import.{}", extension))
+    });
+
+    // Image references only point somewhere real when the images travel with
+    // the import, so rewrite them only when they are bundled into the ZIP.
+    let bundle_images = cli.format == Format::Zip && cli.include_images;
+    let mut export_options = cli.source.export_options()?;
+    let (processed_posts, mut asset_manifest, _) =
+        load_posts(&cli.source, &export_options, bundle_images)?;
+
+    if bundle_images {
+        add_profile_images(&mut export_options, &cli.source.input, &mut asset_manifest);
+        report_assets(&asset_manifest, &cli.source.input, cli.source.recursive)?;
+    }
+
+    report_name_merges(&GhostExporter::name_merges(&processed_posts, &export_options));
+    let mut ghost_import =
+        GhostExporter::create_export_with_options(processed_posts, &export_options)?;
+
+    if let Some(site_export) = &cli.merge_into {
+        let existing = GhostReader::read_file(site_export)?;
+        let (merged, report) = ImportMerger::merge(existing, ghost_import, cli.on_conflict);
+        ghost_import = merged;
+        report_merge(&report, site_export);
+    }
+
+    let report = Validator::new(cli.fix).run(&mut ghost_import);
+    report_validation(&report);
+    if report.errors() > 0 {
+        anyhow::bail!(
+            "{} problems would make Ghost reject the import; fix the sources or rerun with --fix",
+            report.errors()
+        );
+    }
 
     match cli.format {
         Format::Json => {
@@ -133,7 +477,8 @@ fn main() -> anyhow::Result<()> {
             println!("Ghost import JSON saved to: {:?}", output_path);
         }
         Format::Zip => {
-            create_zip_export(&ghost_import, &cli.input, &output_path, cli.include_images, cli.verbose)?;
+            let assets = bundle_images.then_some(&asset_manifest);
+            create_zip_export(&ghost_import, &output_path, assets, cli.source.verbose)?;
             println!("Ghost import ZIP saved to: {:?}", output_path);
         }
     }
@@ -145,11 +490,300 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn report_merge(report: &MergeReport, site_export: &std::path::Path) {
+    println!(
+        "Merged {} new posts into {:?} ({} tags and {} users reused)",
+        report.added, site_export, report.tags_reused, report.users_reused
+    );
+    for slug in &report.skipped {
+        println!("  skipped {}: slug already in use", slug);
+    }
+    for (from, to) in &report.renamed {
+        println!("  renamed {} -> {}", from, to);
+    }
+    for slug in &report.overwritten {
+        println!("  overwrote {}", slug);
+    }
+}
+
+fn report_name_merges(merges: &[NameMerge]) {
+    for merge in merges {
+        let kind = match merge.kind {
+            SlugKind::User => "author",
+            _ => "tag",
+        };
+        let merged: Vec<_> = merge.merged.iter().map(|name| format!("{:?}", name)).collect();
+        println!("merged {} {} into {:?}", kind, merged.join(", "), merge.canonical);
+    }
+}
+
+fn report_validation(report: &ValidationReport) {
+    for issue in &report.fixed {
+        println!("fixed: {}: {}", issue.location(), issue.message);
+    }
+    for issue in &report.issues {
+        eprintln!("{}", issue);
+    }
+}
+
+fn validate(args: ValidateArgs) -> anyhow::Result<()> {
+    let json = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {:?}", args.file))?;
+    // Files gmi wrote deserialize as they are; anything else goes through the
+    // reader, which normalizes Ghost's own exports.
+    let mut ghost_import = match serde_json::from_str::<GhostImport>(&json) {
+        Ok(ghost_import) => ghost_import,
+        Err(_) => GhostReader::parse(&json)
+            .with_context(|| format!("Failed to parse {:?}", args.file))?,
+    };
+
+    let report = Validator::new(args.fix).run(&mut ghost_import);
+    report_validation(&report);
+
+    if args.fix && !report.fixed.is_empty() {
+        let output = args.output.as_ref().unwrap_or(&args.file);
+        std::fs::write(output, GhostExporter::to_json(&ghost_import)?)?;
+        println!("Fixed import saved to: {:?}", output);
+    }
+
+    match report.errors() {
+        0 => {
+            println!(
+                "{:?} is valid ({} warnings)",
+                args.file,
+                report.issues.len()
+            );
+            Ok(())
+        }
+        errors => anyhow::bail!("{} problems would make Ghost reject the import", errors),
+    }
+}
+
+fn admin_client(args: &PublishArgs) -> anyhow::Result<AdminClient> {
+    let key = AdminApiKey::parse(&args.admin_key)?;
+    Ok(AdminClient::new(&args.url, key).with_retry_policy(RetryPolicy {
+        max_retries: args.max_retries,
+        ..Default::default()
+    }))
+}
+
+/// Builds the import for posts headed to a live site, uploading their local
+/// images first and pointing the posts at the uploaded copies.
+fn remote_import(
+    args: &PublishArgs,
+    client: &AdminClient,
+    options: &ExportOptions,
+    processed_posts: Vec<ProcessedMarkdown>,
+    asset_manifest: &AssetManifest,
+) -> anyhow::Result<GhostImport> {
+    report_missing_assets(asset_manifest);
+    report_name_merges(&GhostExporter::name_merges(&processed_posts, options));
+    let mut ghost_import = GhostExporter::create_export_with_options(processed_posts, options)?;
+
+    let cache_path = args
+        .upload_cache
+        .clone()
+        .unwrap_or_else(|| args.source.input.join(".gmi-uploads.json"));
+    let uploads = ImageUploader::new(client, UploadCache::load(&cache_path)?)
+        .upload(asset_manifest)
+        .with_context(|| format!("Failed to upload images to {}", args.url))?;
+    replace_image_urls(&mut ghost_import, &uploads.urls);
+
+    if args.source.verbose {
+        for path in &uploads.uploaded {
+            println!("Uploaded image: {:?}", path);
+        }
+        for path in &uploads.cached {
+            println!("Already uploaded: {:?}", path);
+        }
+    }
+    println!(
+        "Uploaded {} images ({} already on the site)",
+        uploads.uploaded.len(),
+        uploads.cached.len()
+    );
+
+    Ok(ghost_import)
+}
+
+fn publish(args: PublishArgs) -> anyhow::Result<()> {
+    let client = admin_client(&args)?;
+    let mut options = args.source.export_options()?;
+    let (processed_posts, mut asset_manifest, _) = load_posts(&args.source, &options, true)?;
+    add_profile_images(&mut options, &args.source.input, &mut asset_manifest);
+    let ghost_import = remote_import(&args, &client, &options, processed_posts, &asset_manifest)?;
+
+    let report = Publisher::new(&client)
+        .publish(&ghost_import)
+        .with_context(|| format!("Failed to publish to {}", args.url))?;
+
+    if args.source.verbose {
+        for post in &report.published {
+            println!("Published: {} ({})", post.slug, post.url.as_deref().unwrap_or(&post.id));
+        }
+        for name in &report.tags_created {
+            println!("Created tag: {}", name);
+        }
+    }
+    report_unknown_authors(&report.unknown_authors);
+    for (slug, error) in &report.failed {
+        eprintln!("Failed to publish {}: {}", slug, error);
+    }
+
+    println!("Published {} posts to {}", report.published.len(), args.url);
+    if !report.failed.is_empty() {
+        anyhow::bail!("{} posts could not be published", report.failed.len());
+    }
+
+    Ok(())
+}
+
+fn sync(args: SyncArgs) -> anyhow::Result<()> {
+    let target = &args.target;
+    let client = admin_client(target)?;
+    let state_path = args
+        .state
+        .clone()
+        .unwrap_or_else(|| target.source.input.join(".gmi-sync.json"));
+    let mut state = SyncState::load(&state_path, client.site_url())?;
+
+    let mut options = target.source.export_options()?;
+    let (mut processed_posts, mut asset_manifest, failed) =
+        load_posts(&target.source, &options, true)?;
+    add_profile_images(&mut options, &target.source.input, &mut asset_manifest);
+    // A post whose file did not load would look removed, so nothing is pruned
+    let prune = if failed.is_empty() || args.prune == PruneMode::Keep {
+        args.prune
+    } else {
+        eprintln!(
+            "Warning: {} files could not be processed; nothing is pruned this run",
+            failed.len()
+        );
+        PruneMode::Keep
+    };
+    state.pin_dates(&mut processed_posts, &target.source.input, chrono::Utc::now());
+    let ghost_import =
+        remote_import(target, &client, &options, processed_posts, &asset_manifest)?;
+
+    let report = Syncer::new(&client, &target.source.input)
+        .with_force(args.force)
+        .sync(&ghost_import, &mut state, prune)
+        .with_context(|| format!("Failed to sync with {}", target.url))?;
+
+    if target.source.verbose {
+        for (label, keys) in [
+            ("Created", &report.created),
+            ("Updated", &report.updated),
+            ("Unchanged", &report.unchanged),
+            ("Pruned", &report.pruned),
+        ] {
+            for key in keys {
+                println!("{}: {}", label, key);
+            }
+        }
+        for name in &report.tags_created {
+            println!("Created tag: {}", name);
+        }
+    }
+    for key in &report.conflicts {
+        eprintln!("Warning: {} was edited in Ghost since the last sync and was left alone; use --force to overwrite it", key);
+    }
+    for key in &report.overwritten {
+        eprintln!("Warning: {} was edited in Ghost since the last sync; the edits were overwritten", key);
+    }
+    for key in &report.orphaned {
+        eprintln!("Warning: the source of {} is gone; use --prune to unpublish or delete it", key);
+    }
+    report_unknown_authors(&report.unknown_authors);
+    for (key, error) in &report.failed {
+        eprintln!("Failed to sync {}: {}", key, error);
+    }
+
+    println!(
+        "Synced with {}: {} created, {} updated, {} unchanged, {} pruned, {} conflicts",
+        target.url,
+        report.created.len(),
+        report.updated.len(),
+        report.unchanged.len(),
+        report.pruned.len(),
+        report.conflicts.len()
+    );
+    if !report.failed.is_empty() {
+        anyhow::bail!("{} posts could not be synced", report.failed.len());
+    }
+
+    Ok(())
+}
+
+fn export_markdown(args: ExportMdArgs) -> anyhow::Result<()> {
+    let ghost_import = GhostReader::read_file(&args.export)?;
+    let options = MarkdownExportOptions {
+        content_dir: args.content_dir,
+        site_url: args.site_url,
+    };
+    let report = MarkdownExporter::export(&ghost_import, &args.output_dir, &options)?;
+
+    if args.verbose {
+        for file in &report.files {
+            println!("Wrote: {:?}", file);
+        }
+        for image in &report.images {
+            println!("Saved image: {:?}", image);
+        }
+    }
+    for (slug, src) in &report.missing_images {
+        eprintln!("Warning: could not fetch image {} used by {}", src, slug);
+    }
+
+    println!(
+        "Exported {} posts and {} images to {:?}",
+        report.files.len(),
+        report.images.len(),
+        args.output_dir
+    );
+    Ok(())
+}
+
+fn report_unknown_authors(names: &[String]) {
+    for name in names {
+        eprintln!(
+            "Warning: no staff user matches author {:?}; their posts fall back to the site owner",
+            name
+        );
+    }
+}
+
+fn report_missing_assets(manifest: &AssetManifest) {
+    for (post, missing) in manifest.missing_by_post() {
+        eprintln!("Warning: {} references {} missing image(s):", post, missing.len());
+        for asset in missing {
+            eprintln!("  {} (expected at {:?})", asset.reference, asset.expected_path);
+        }
+    }
+}
+
+fn report_assets(
+    manifest: &AssetManifest,
+    input_dir: &std::path::Path,
+    recursive: bool,
+) -> anyhow::Result<()> {
+    report_missing_assets(manifest);
+
+    let orphans = manifest.find_orphans(input_dir, recursive)?;
+    if !orphans.is_empty() {
+        println!("{} unreferenced image(s) were not bundled:", orphans.len());
+        for orphan in orphans {
+            println!("  {:?}", orphan);
+        }
+    }
+
+    Ok(())
+}
+
 fn create_zip_export(
     ghost_import: &models::ghost::GhostImport,
-    input_dir: &std::path::Path,
     output_path: &std::path::Path,
-    include_images: bool,
+    assets: Option<&AssetManifest>,
     verbose: bool,
 ) -> anyhow::Result<()> {
     use std::io::Write;
@@ -167,23 +801,18 @@ fn create_zip_export(
     zip.start_file("ghost-import.json", options)?;
     zip.write_all(json.as_bytes())?;
 
-    // Add images if requested
-    if include_images {
-        let image_files = utils::FileOps::find_image_files(input_dir, true)?;
-        
+    // Add the images posts reference
+    if let Some(assets) = assets {
         if verbose {
-            println!("Including {} images in ZIP", image_files.len());
+            println!("Including {} images in ZIP", assets.files().len());
         }
 
-        for image_file in image_files {
-            let relative_path = utils::FileOps::get_relative_path(&image_file, input_dir)?;
-            let zip_path = PathBuf::from("content/images").join(relative_path);
-            
+        for (zip_path, image_file) in assets.files() {
             if verbose {
                 println!("Adding image: {:?} -> {:?}", image_file, zip_path);
             }
 
-            zip.start_file(zip_path.to_string_lossy(), options)?;
+            zip.start_file(zip_path.as_str(), options)?;
             let image_data = std::fs::read(image_file)?;
             zip.write_all(&image_data)?;
         }
@@ -1,6 +1,217 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Ghost major release an import is generated for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum GhostVersion {
+    #[value(name = "2")]
+    V2,
+    #[value(name = "3")]
+    V3,
+    #[value(name = "4")]
+    V4,
+    #[default]
+    #[value(name = "5")]
+    V5,
+}
+
+/// Import features that only some Ghost versions understand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFeature {
+    LexicalContent,
+    MobiledocContent,
+    /// Posts for members or paying members only
+    MembersVisibility,
+    /// Posts for members of specific tiers
+    TierVisibility,
+}
+
+impl GhostVersion {
+    /// Version string written to `meta.version`, the last release of the line.
+    pub fn export_version(self) -> &'static str {
+        match self {
+            GhostVersion::V2 => "2.38.3",
+            GhostVersion::V3 => "3.42.9",
+            GhostVersion::V4 => "4.48.9",
+            GhostVersion::V5 => "5.75.1",
+        }
+    }
+
+    pub fn supports(self, feature: SchemaFeature) -> bool {
+        match feature {
+            SchemaFeature::LexicalContent => self >= GhostVersion::V5,
+            SchemaFeature::MobiledocContent => true,
+            SchemaFeature::MembersVisibility => self >= GhostVersion::V3,
+            SchemaFeature::TierVisibility => self >= GhostVersion::V5,
+        }
+    }
+
+    pub fn ensure(self, feature: SchemaFeature) -> anyhow::Result<()> {
+        if !self.supports(feature) {
+            anyhow::bail!(
+                "Ghost {} cannot import {}",
+                self.export_version(),
+                match feature {
+                    SchemaFeature::LexicalContent => "lexical content; use mobiledoc or html",
+                    SchemaFeature::MobiledocContent => "mobiledoc content",
+                    SchemaFeature::MembersVisibility => "members-only or paid posts",
+                    SchemaFeature::TierVisibility => "posts restricted to tiers",
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Posts carry a single `author_id` next to `posts_authors` before 5.x.
+    pub fn has_post_author_id(self) -> bool {
+        self < GhostVersion::V5
+    }
+
+    /// Pre-5.x importers expect `plaintext` rather than deriving it.
+    pub fn has_plaintext(self) -> bool {
+        self < GhostVersion::V5
+    }
+
+    /// SEO and social fields moved from `posts` into `posts_meta` in 3.x.
+    pub fn has_posts_meta(self) -> bool {
+        self >= GhostVersion::V3
+    }
+
+    /// Feature images gained alt text and a caption in 4.x.
+    pub fn has_feature_image_caption(self) -> bool {
+        self >= GhostVersion::V4
+    }
+
+    /// Tags gained an accent colour in 4.x.
+    pub fn has_tag_accent_color(self) -> bool {
+        self >= GhostVersion::V4
+    }
+}
+
+/// How post bodies are stored in the import.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContentFormat {
+    /// Rendered HTML only; Ghost converts it on import
+    #[default]
+    Html,
+    /// Ghost 5 Lexical editor document, with the HTML alongside
+    Lexical,
+    /// Ghost 2.x-4.x mobiledoc document, with the HTML alongside
+    Mobiledoc,
+}
+
+/// How Markdown is laid out in a mobiledoc document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MobiledocMode {
+    /// The original Markdown in a single Ghost markdown card
+    #[default]
+    MarkdownCard,
+    /// Native paragraphs, headings and lists, with cards for the rest
+    Sections,
+}
+
+/// Staff role an imported user is given.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, clap::ValueEnum,
+)]
+#[serde(try_from = "String")]
+pub enum StaffRole {
+    Administrator,
+    Editor,
+    /// Ghost's role for new staff
+    #[default]
+    Author,
+    Contributor,
+}
+
+impl StaffRole {
+    /// Role name as Ghost stores it; the importer matches roles by name.
+    pub fn name(self) -> &'static str {
+        match self {
+            StaffRole::Administrator => "Administrator",
+            StaffRole::Editor => "Editor",
+            StaffRole::Author => "Author",
+            StaffRole::Contributor => "Contributor",
+        }
+    }
+}
+
+impl TryFrom<String> for StaffRole {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        <Self as clap::ValueEnum>::from_str(value.trim(), true)
+            .map_err(|_| format!("unknown staff role {:?}", value))
+    }
+}
+
+/// Publication states Ghost accepts for a post.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PostStatus {
+    #[default]
+    Published,
+    Draft,
+    /// Published automatically at `published_at`
+    Scheduled,
+    /// Sent as a newsletter without appearing on the site
+    Sent,
+}
+
+impl PostStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            PostStatus::Published => "published",
+            PostStatus::Draft => "draft",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Sent => "sent",
+        }
+    }
+}
+
+impl TryFrom<String> for PostStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        <Self as clap::ValueEnum>::from_str(value.trim(), true).map_err(|_| {
+            format!("unknown status {:?}; Ghost accepts published, draft, scheduled or sent", value)
+        })
+    }
+}
+
+/// Who can read a post past its public preview.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PostVisibility {
+    #[default]
+    Public,
+    /// Signed-in members, free or paid
+    Members,
+    /// Paying members of any tier
+    Paid,
+    /// Members of the tiers the post lists
+    Tiers,
+}
+
+impl PostVisibility {
+    pub fn name(self) -> &'static str {
+        match self {
+            PostVisibility::Public => "public",
+            PostVisibility::Members => "members",
+            PostVisibility::Paid => "paid",
+            PostVisibility::Tiers => "tiers",
+        }
+    }
+}
+
+impl TryFrom<String> for PostVisibility {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        <Self as clap::ValueEnum>::from_str(value.trim(), true).map_err(|_| {
+            format!("unknown visibility {:?}; Ghost accepts public, members, paid or tiers", value)
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GhostImport {
     pub meta: Meta,
@@ -24,6 +235,15 @@ pub struct Data {
     pub posts_authors: Vec<PostsAuthors>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub roles_users: Vec<RolesUsers>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<Role>,
+    /// Membership tiers; Ghost still calls the table `products`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub products: Vec<Product>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub posts_products: Vec<PostsProducts>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub posts_meta: Vec<PostsMeta>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -33,6 +253,12 @@ pub struct Post {
     pub slug: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub html: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lexical: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mobiledoc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plaintext: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub feature_image: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -49,11 +275,18 @@ pub struct Post {
     pub meta_title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
     pub visibility: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub show_title_and_feature_image: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email_only: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_id: Option<i32>,
+    /// Markdown file the post was built from; never part of the import
+    #[serde(skip)]
+    pub source_path: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -69,6 +302,12 @@ pub struct Tag {
     pub meta_title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent_color: Option<String>,
+    /// `internal` for `#name` tags, which themes and routes use but readers
+    /// never see
+    #[serde(default = "Tag::public")]
+    pub visibility: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -102,6 +341,9 @@ pub struct PostsTags {
     pub id: i32,
     pub post_id: i32,
     pub tag_id: i32,
+    /// Position among the post's tags; the first is the primary tag
+    #[serde(default)]
+    pub sort_order: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -109,6 +351,49 @@ pub struct PostsAuthors {
     pub id: i32,
     pub post_id: i32,
     pub author_id: i32,
+    /// Position among the post's authors; the first is the primary author
+    #[serde(default)]
+    pub sort_order: i32,
+}
+
+/// SEO, social and email fields of a post, which Ghost keeps out of `posts`
+/// since 3.x.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PostsMeta {
+    pub id: i32,
+    pub post_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twitter_image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twitter_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twitter_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_subject: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_image_alt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_image_caption: Option<String>,
+}
+
+/// Tier a `tiers` post is available to.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostsProducts {
+    pub id: i32,
+    pub post_id: i32,
+    pub product_id: i32,
+    #[serde(default)]
+    pub sort_order: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -118,15 +403,85 @@ pub struct RolesUsers {
     pub role_id: i32,
 }
 
-impl Default for Meta {
-    fn default() -> Self {
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Role {
+    pub id: i32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Role {
+    pub fn new(id: i32, role: StaffRole) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id,
+            name: role.name().to_string(),
+            description: Some(format!("{}s", role.name())),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// A membership tier.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Product {
+    pub id: i32,
+    pub name: String,
+    pub slug: String,
+    /// `paid`, or `free` for the tier every member has
+    #[serde(default = "Product::paid")]
+    pub r#type: String,
+    #[serde(default = "Product::active")]
+    pub active: bool,
+    #[serde(default = "Tag::public")]
+    pub visibility: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Product {
+    pub fn new(id: i32, name: &str, slug: String) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id,
+            name: name.to_string(),
+            slug,
+            r#type: Product::paid(),
+            active: true,
+            visibility: Tag::public(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn paid() -> String {
+        "paid".to_string()
+    }
+
+    fn active() -> bool {
+        true
+    }
+}
+
+impl Meta {
+    pub fn for_version(version: GhostVersion) -> Self {
         Self {
             exported_on: chrono::Utc::now().timestamp_millis(),
-            version: "5.75.1".to_string(),
+            version: version.export_version().to_string(),
         }
     }
 }
 
+impl Default for Meta {
+    fn default() -> Self {
+        Self::for_version(GhostVersion::default())
+    }
+}
+
 impl Default for Post {
     fn default() -> Self {
         let now = chrono::Utc::now();
@@ -135,6 +490,9 @@ impl Default for Post {
             title: String::new(),
             slug: String::new(),
             html: None,
+            lexical: None,
+            mobiledoc: None,
+            plaintext: None,
             feature_image: None,
             featured: Some(0),
             status: "published".to_string(),
@@ -145,13 +503,29 @@ impl Default for Post {
             custom_excerpt: None,
             meta_title: None,
             meta_description: None,
+            canonical_url: None,
             visibility: "public".to_string(),
             show_title_and_feature_image: Some(1),
             email_only: Some(0),
+            author_id: None,
+            source_path: None,
         }
     }
 }
 
+impl Tag {
+    /// Prefix Ghost gives the names of internal tags.
+    pub const INTERNAL_PREFIX: char = '#';
+
+    pub fn is_internal(name: &str) -> bool {
+        name.starts_with(Self::INTERNAL_PREFIX)
+    }
+
+    fn public() -> String {
+        "public".to_string()
+    }
+}
+
 impl Default for Tag {
     fn default() -> Self {
         let now = chrono::Utc::now();
@@ -163,6 +537,8 @@ impl Default for Tag {
             feature_image: None,
             meta_title: None,
             meta_description: None,
+            accent_color: None,
+            visibility: Tag::public(),
             created_at: now,
             updated_at: now,
         }
@@ -188,4 +564,37 @@ impl Default for User {
             updated_at: now,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_schema() {
+        assert_eq!(Meta::for_version(GhostVersion::V3).version, "3.42.9");
+        assert_eq!(Meta::default().version, "5.75.1");
+
+        assert!(GhostVersion::V5.supports(SchemaFeature::LexicalContent));
+        assert!(GhostVersion::V4.ensure(SchemaFeature::LexicalContent).is_err());
+        assert!(GhostVersion::V2.ensure(SchemaFeature::MobiledocContent).is_ok());
+        assert!(GhostVersion::V2.ensure(SchemaFeature::MembersVisibility).is_err());
+        assert!(GhostVersion::V4.supports(SchemaFeature::MembersVisibility));
+        assert!(!GhostVersion::V4.supports(SchemaFeature::TierVisibility));
+
+        assert!(GhostVersion::V4.has_post_author_id());
+        assert!(!GhostVersion::V5.has_plaintext());
+        assert!(!GhostVersion::V2.has_posts_meta());
+        assert!(GhostVersion::V3.has_posts_meta());
+        assert!(!GhostVersion::V3.has_feature_image_caption());
+    }
+
+    #[test]
+    fn test_staff_role_names() {
+        let role: StaffRole = serde_yaml::from_str("editor").unwrap();
+        assert_eq!(role, StaffRole::Editor);
+        assert_eq!(StaffRole::try_from("Administrator".to_string()), Ok(StaffRole::Administrator));
+        assert!(StaffRole::try_from("owner".to_string()).is_err());
+        assert_eq!(Role::new(1, StaffRole::Contributor).description.as_deref(), Some("Contributors"));
+    }
+}
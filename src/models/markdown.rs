@@ -7,11 +7,15 @@ pub struct Frontmatter {
     pub date: Option<String>,
     pub author: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Internal tags, with or without the leading `#`
+    pub internal_tags: Option<Vec<String>>,
     pub slug: Option<String>,
     pub description: Option<String>,
     pub summary: Option<String>,
     pub featured: Option<bool>,
     pub status: Option<String>,
+    /// `public`, `members`, `paid` or `tiers`
+    pub visibility: Option<String>,
     pub image: Option<String>,
     pub images: Option<Vec<String>>,
     pub category: Option<String>,
@@ -22,13 +26,119 @@ pub struct Frontmatter {
     pub extra: HashMap<String, serde_yaml::Value>,
 }
 
-#[derive(Debug, Clone)]
+/// SEO, social and email fields of a post, as written in its frontmatter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeoFields {
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    pub canonical_url: Option<String>,
+    pub og_title: Option<String>,
+    pub og_description: Option<String>,
+    pub og_image: Option<String>,
+    pub twitter_title: Option<String>,
+    pub twitter_description: Option<String>,
+    pub twitter_image: Option<String>,
+    pub email_subject: Option<String>,
+    pub feature_image_alt: Option<String>,
+    pub feature_image_caption: Option<String>,
+}
+
+impl Frontmatter {
+    /// SEO fields from flat keys such as `seo_title` and `og_image`, or from
+    /// the nested `seo:` and `cover:` blocks Hugo themes use; the first key
+    /// present wins.
+    pub fn seo(&self) -> SeoFields {
+        SeoFields {
+            meta_title: self.text(&["meta_title", "seo_title", "seo.title", "seo.meta_title"]),
+            meta_description: self.text(&[
+                "meta_description",
+                "seo_description",
+                "seo.description",
+                "seo.meta_description",
+            ]),
+            canonical_url: self.text(&[
+                "canonical_url",
+                "canonicalURL",
+                "canonicalUrl",
+                "canonical",
+                "seo.canonical_url",
+                "seo.canonical",
+            ]),
+            og_title: self.text(&["og_title", "seo.og_title"]),
+            og_description: self.text(&["og_description", "seo.og_description"]),
+            og_image: self.text(&["og_image", "seo.og_image", "seo.image"]),
+            twitter_title: self.text(&["twitter_title", "seo.twitter_title"]),
+            twitter_description: self.text(&["twitter_description", "seo.twitter_description"]),
+            twitter_image: self.text(&["twitter_image", "seo.twitter_image"]),
+            email_subject: self.text(&["email_subject"]),
+            feature_image_alt: self.text(&["feature_image_alt", "image_alt", "cover.alt"]),
+            feature_image_caption: self.text(&["feature_image_caption", "image_caption", "cover.caption"]),
+        }
+    }
+
+    /// The first non-empty string among `keys`; `block.key` looks inside a
+    /// nested mapping.
+    fn text(&self, keys: &[&str]) -> Option<String> {
+        keys.iter().find_map(|key| {
+            let value = match key.split_once('.') {
+                Some((block, field)) => self.extra.get(block)?.get(field)?,
+                None => self.extra.get(*key)?,
+            };
+            let value = value.as_str()?.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        })
+    }
+
+    /// Categories from `category` and the Jekyll, Hexo and Hugo `categories`
+    /// list. A flat list is read as Jekyll and Hugo mean it, one category per
+    /// item, even though Hexo reads `[Diary, Life]` as a single hierarchy.
+    /// Each inner list of a nested one, as in Hexo's `[[Diary, Life]]`, is a
+    /// hierarchy and comes back as `Diary/Life`.
+    pub fn categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> = self.category.iter().cloned().collect();
+        match self.extra.get("categories") {
+            Some(serde_yaml::Value::String(category)) => categories.push(category.clone()),
+            Some(serde_yaml::Value::Sequence(items)) => {
+                for item in items {
+                    match item {
+                        serde_yaml::Value::String(category) => categories.push(category.clone()),
+                        serde_yaml::Value::Sequence(path) => {
+                            let path: Vec<&str> = path.iter().filter_map(|p| p.as_str()).collect();
+                            categories.push(path.join("/"));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        categories.retain(|category| !category.trim().is_empty());
+        categories
+    }
+
+    /// Names of the tiers a `tiers` post is for, from a list or a single name.
+    pub fn tiers(&self) -> Vec<String> {
+        let tiers = match self.extra.get("tiers") {
+            Some(serde_yaml::Value::String(tier)) => vec![tier.clone()],
+            Some(serde_yaml::Value::Sequence(items)) => {
+                items.iter().filter_map(|t| t.as_str()).map(str::to_string).collect()
+            }
+            _ => Vec::new(),
+        };
+        tiers.into_iter().filter(|tier| !tier.trim().is_empty()).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ProcessedMarkdown {
     pub frontmatter: Frontmatter,
     pub content: String,
     pub html_content: String,
     pub file_path: String,
     pub images: Vec<String>,
+    /// Image references as written in the Markdown, mapped to the URL they
+    /// were rewritten to.
+    pub asset_urls: HashMap<String, String>,
 }
 
 impl Default for Frontmatter {
@@ -38,11 +148,13 @@ impl Default for Frontmatter {
             date: None,
             author: None,
             tags: None,
+            internal_tags: None,
             slug: None,
             description: None,
             summary: None,
             featured: Some(false),
             status: Some("published".to_string()),
+            visibility: None,
             image: None,
             images: None,
             category: None,
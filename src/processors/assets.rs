@@ -0,0 +1,462 @@
+use crate::models::ProcessedMarkdown;
+use crate::utils::FileOps;
+use anyhow::Result;
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+
+/// URL prefix Ghost uses for files stored under `content/images` in an import.
+pub const GHOST_IMAGES_URL: &str = "__GHOST_URL__/content/images";
+
+/// Directory inside the ZIP export that Ghost copies into its image storage.
+pub const ZIP_IMAGES_DIR: &str = "content/images";
+
+/// Folders that commonly hold site-absolute assets (`/images/a.png`) in
+/// static site generators such as Hugo (`static/`) or Next.js (`public/`).
+const STATIC_DIRS: [&str; 2] = ["public", "static"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAsset {
+    /// The reference exactly as written in the post.
+    pub original: String,
+    /// Location of the file on disk.
+    pub source_path: PathBuf,
+    /// Path of the file inside the ZIP export.
+    pub zip_path: String,
+    /// URL that replaces the reference in the imported post.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingAsset {
+    /// Markdown file that holds the reference.
+    pub post: String,
+    /// The reference exactly as written in the post.
+    pub reference: String,
+    /// Where the file was expected on disk.
+    pub expected_path: PathBuf,
+}
+
+/// The set of images that actually go into an export, built from the
+/// references posts make rather than from whatever lies in the input tree.
+#[derive(Debug, Default)]
+pub struct AssetManifest {
+    files: BTreeMap<String, PathBuf>,
+    urls: BTreeMap<String, PathBuf>,
+    missing: Vec<MissingAsset>,
+}
+
+impl AssetManifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the assets referenced by one post. Files that exist are queued
+    /// for bundling; the rest are kept as missing for that post.
+    pub fn add_post(&mut self, post: &str, assets: Vec<ResolvedAsset>) {
+        for asset in assets {
+            if asset.source_path.is_file() {
+                self.urls.entry(asset.url).or_insert_with(|| asset.source_path.clone());
+                self.files.entry(asset.zip_path).or_insert(asset.source_path);
+            } else {
+                self.missing.push(MissingAsset {
+                    post: post.to_string(),
+                    reference: asset.original,
+                    expected_path: asset.source_path,
+                });
+            }
+        }
+    }
+
+    /// Files to bundle, keyed by their path inside the ZIP.
+    pub fn files(&self) -> &BTreeMap<String, PathBuf> {
+        &self.files
+    }
+
+    /// Files to upload, keyed by the URL posts reference them with.
+    pub fn by_url(&self) -> &BTreeMap<String, PathBuf> {
+        &self.urls
+    }
+
+    pub fn missing(&self) -> &[MissingAsset] {
+        &self.missing
+    }
+
+    /// Missing references grouped by the post that makes them.
+    pub fn missing_by_post(&self) -> BTreeMap<&str, Vec<&MissingAsset>> {
+        let mut report: BTreeMap<&str, Vec<&MissingAsset>> = BTreeMap::new();
+        for missing in &self.missing {
+            report.entry(missing.post.as_str()).or_default().push(missing);
+        }
+        report
+    }
+
+    /// Images under `root` that no post references.
+    pub fn find_orphans(&self, root: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+        let referenced: BTreeSet<PathBuf> =
+            self.files.values().map(|path| normalize_path(path)).collect();
+
+        Ok(FileOps::find_image_files(root, recursive)?
+            .into_iter()
+            .filter(|image| !referenced.contains(&normalize_path(image)))
+            .collect())
+    }
+}
+
+pub struct AssetResolver {
+    root: PathBuf,
+}
+
+impl AssetResolver {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: normalize_path(root),
+        }
+    }
+
+    /// Resolves an image reference found in `source_file` to the file it points
+    /// at and the `content/images` location it gets inside the export.
+    /// Remote URLs and data URIs are left alone and yield `None`.
+    pub fn resolve(&self, src: &str, source_file: &Path) -> Option<ResolvedAsset> {
+        let src = src.trim();
+        if src.is_empty() || Self::is_external(src) {
+            return None;
+        }
+
+        let path_part = src.split(['?', '#']).next().unwrap_or(src);
+        let decoded = percent_decode(path_part);
+        if decoded.is_empty() {
+            return None;
+        }
+
+        let source_path = if let Some(site_path) = decoded.strip_prefix('/') {
+            self.resolve_site_absolute(site_path)
+        } else {
+            let base = source_file.parent().unwrap_or_else(|| Path::new(""));
+            normalize_path(&base.join(&decoded))
+        };
+
+        // With `.` as the root every relative path strips, `../x.png` included
+        let relative = match source_path.strip_prefix(&self.root) {
+            Ok(relative) if relative.components().all(|c| matches!(c, Component::Normal(_))) => {
+                relative.to_path_buf()
+            }
+            _ => Self::external_path(&source_path)?,
+        };
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Some(ResolvedAsset {
+            original: src.to_string(),
+            zip_path: format!("{}/{}", ZIP_IMAGES_DIR, relative),
+            url: format!("{}/{}", GHOST_IMAGES_URL, relative.replace(' ', "%20")),
+            source_path,
+        })
+    }
+
+    /// Rewrites every local image reference of a post (HTML `<img>` tags and
+    /// the frontmatter `image`/`images` fields) to its Ghost URL and returns
+    /// the assets that were referenced. Open Graph and Twitter images are
+    /// only recorded in `asset_urls`, since they may sit in a nested block.
+    pub fn rewrite_post(&self, processed: &mut ProcessedMarkdown) -> Vec<ResolvedAsset> {
+        let source_file = PathBuf::from(&processed.file_path);
+        let mut assets: Vec<ResolvedAsset> = Vec::new();
+        let asset_urls = &mut processed.asset_urls;
+
+        let mut rewrite = |src: &str| -> Option<String> {
+            let asset = self.resolve(src, &source_file)?;
+            let url = asset.url.clone();
+            asset_urls.insert(src.to_string(), url.clone());
+            if !assets.iter().any(|a| a.source_path == asset.source_path) {
+                assets.push(asset);
+            }
+            Some(url)
+        };
+
+        let re = Regex::new(r#"(<img\b[^>]*?\bsrc=)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        processed.html_content = re
+            .replace_all(&processed.html_content, |caps: &Captures| {
+                let (src, quote) = match caps.get(2) {
+                    Some(m) => (m.as_str(), '"'),
+                    None => (caps.get(3).map_or("", |m| m.as_str()), '\''),
+                };
+                match rewrite(&src.replace("&amp;", "&")) {
+                    Some(url) => format!("{}{}{}{}", &caps[1], quote, url, quote),
+                    None => caps[0].to_string(),
+                }
+            })
+            .to_string();
+
+        if let Some(image) = processed.frontmatter.image.as_mut() {
+            if let Some(url) = rewrite(image) {
+                *image = url;
+            }
+        }
+        if let Some(images) = processed.frontmatter.images.as_mut() {
+            for image in images.iter_mut() {
+                if let Some(url) = rewrite(image) {
+                    *image = url;
+                }
+            }
+        }
+        let seo = processed.frontmatter.seo();
+        for image in [seo.og_image, seo.twitter_image].into_iter().flatten() {
+            rewrite(&image);
+        }
+
+        assets
+    }
+
+    /// `_external/<hash>/<file name>` for a file outside the root. The hash
+    /// of the canonical path keeps same-named files from different
+    /// directories apart.
+    fn external_path(source_path: &Path) -> Option<PathBuf> {
+        let file_name = source_path.file_name()?;
+        let canonical = std::fs::canonicalize(source_path).unwrap_or_else(|_| source_path.to_path_buf());
+        let hash = hex::encode(Sha256::digest(canonical.to_string_lossy().as_bytes()));
+        Some(Path::new("_external").join(&hash[..8]).join(file_name))
+    }
+
+    fn resolve_site_absolute(&self, site_path: &str) -> PathBuf {
+        let direct = normalize_path(&self.root.join(site_path));
+        if direct.exists() {
+            return direct;
+        }
+        STATIC_DIRS
+            .iter()
+            .map(|dir| normalize_path(&self.root.join(dir).join(site_path)))
+            .find(|candidate| candidate.exists())
+            .unwrap_or(direct)
+    }
+
+    fn is_external(src: &str) -> bool {
+        let lower = src.to_lowercase();
+        lower.contains("://")
+            || lower.starts_with("//")
+            || lower.starts_with("data:")
+            || lower.starts_with("mailto:")
+            || lower.starts_with('#')
+            || src.starts_with("__GHOST_URL__")
+    }
+}
+
+/// Lexically normalises a path, dropping `.` and folding `..` without touching
+/// the filesystem, so references to files that do not exist can still be
+/// compared against the input root.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                let can_pop = matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                );
+                if can_pop {
+                    normalized.pop();
+                } else if !normalized.has_root() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+pub(crate) fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = (
+                (bytes[i + 1] as char).to_digit(16),
+                (bytes[i + 2] as char).to_digit(16),
+            );
+            if let (Some(high), Some(low)) = hex {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processed(file_path: &str, html: &str) -> ProcessedMarkdown {
+        ProcessedMarkdown {
+            html_content: html.to_string(),
+            file_path: file_path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_relative_to_source_file() {
+        let resolver = AssetResolver::new(Path::new("./blog"));
+
+        let asset = resolver
+            .resolve("./img/a.png", Path::new("./blog/posts/first.md"))
+            .unwrap();
+        assert_eq!(asset.source_path, PathBuf::from("blog/posts/img/a.png"));
+        assert_eq!(asset.zip_path, "content/images/posts/img/a.png");
+        assert_eq!(asset.url, "__GHOST_URL__/content/images/posts/img/a.png");
+
+        let asset = resolver
+            .resolve("../assets/my%20photo.jpg?v=2", Path::new("blog/posts/first.md"))
+            .unwrap();
+        assert_eq!(asset.source_path, PathBuf::from("blog/assets/my photo.jpg"));
+        assert_eq!(asset.url, "__GHOST_URL__/content/images/assets/my%20photo.jpg");
+    }
+
+    #[test]
+    fn test_resolve_skips_remote_urls() {
+        let resolver = AssetResolver::new(Path::new("blog"));
+        let post = Path::new("blog/post.md");
+
+        assert!(resolver.resolve("https://example.com/a.png", post).is_none());
+        assert!(resolver.resolve("//cdn.example.com/a.png", post).is_none());
+        assert!(resolver.resolve("data:image/png;base64,AAAA", post).is_none());
+        assert!(resolver.resolve("__GHOST_URL__/content/images/a.png", post).is_none());
+    }
+
+    #[test]
+    fn test_resolve_site_absolute_and_outside_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("static/images")).unwrap();
+        std::fs::write(root.join("static/images/logo.png"), b"png").unwrap();
+
+        let resolver = AssetResolver::new(root);
+        let post = root.join("posts/a.md");
+
+        let asset = resolver.resolve("/images/logo.png", &post).unwrap();
+        assert_eq!(asset.zip_path, "content/images/static/images/logo.png");
+
+        let asset = resolver.resolve("../../shared/b.gif", &post).unwrap();
+        assert!(asset.zip_path.starts_with("content/images/_external/"));
+        assert!(asset.zip_path.ends_with("/b.gif"));
+    }
+
+    #[test]
+    fn test_resolve_with_current_directory_root() {
+        for root in [".", "./"] {
+            let resolver = AssetResolver::new(Path::new(root));
+            let post = Path::new("./posts/a.md");
+
+            let asset = resolver.resolve("img/a.png", post).unwrap();
+            assert_eq!(asset.zip_path, "content/images/posts/img/a.png");
+
+            let asset = resolver.resolve("../../shared/x.png", post).unwrap();
+            assert!(asset.zip_path.starts_with("content/images/_external/"));
+            assert!(!asset.zip_path.contains(".."));
+        }
+    }
+
+    #[test]
+    fn test_external_files_with_the_same_name() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base = temp_dir.path();
+        for dir in ["one", "two"] {
+            std::fs::create_dir_all(base.join(dir)).unwrap();
+            std::fs::write(base.join(dir).join("cover.png"), dir).unwrap();
+        }
+
+        let resolver = AssetResolver::new(&base.join("blog"));
+        let post = base.join("blog/a.md");
+        let one = resolver.resolve("../one/cover.png", &post).unwrap();
+        let two = resolver.resolve("../two/cover.png", &post).unwrap();
+        assert_ne!(one.zip_path, two.zip_path);
+        assert_ne!(one.url, two.url);
+
+        let mut manifest = AssetManifest::new();
+        manifest.add_post("a.md", vec![one, two]);
+        assert_eq!(manifest.files().len(), 2);
+    }
+
+    #[test]
+    fn test_rewrite_post() {
+        let resolver = AssetResolver::new(Path::new("blog"));
+        let mut post = processed(
+            "blog/posts/a.md",
+            "<p><img src=\"./img/a.png\" alt=\"a\" /><img src=\"https://x.org/b.png\" /></p>",
+        );
+        post.frontmatter.image = Some("img/cover.jpg".to_string());
+        post.frontmatter.images = Some(vec!["https://x.org/c.jpg".to_string()]);
+        post.frontmatter.extra.insert("seo".into(), serde_yaml::from_str("image: /social/og.png").unwrap());
+
+        let assets = resolver.rewrite_post(&mut post);
+
+        assert_eq!(
+            post.html_content,
+            "<p><img src=\"__GHOST_URL__/content/images/posts/img/a.png\" alt=\"a\" /><img src=\"https://x.org/b.png\" /></p>"
+        );
+        assert_eq!(
+            post.frontmatter.image.as_deref(),
+            Some("__GHOST_URL__/content/images/posts/img/cover.jpg")
+        );
+        assert_eq!(post.frontmatter.images.unwrap()[0], "https://x.org/c.jpg");
+        assert_eq!(assets.len(), 3);
+        assert_eq!(
+            post.asset_urls["./img/a.png"],
+            "__GHOST_URL__/content/images/posts/img/a.png"
+        );
+        assert_eq!(
+            post.asset_urls["/social/og.png"],
+            "__GHOST_URL__/content/images/social/og.png"
+        );
+    }
+
+    #[test]
+    fn test_manifest_bundles_only_referenced_images() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("posts/img")).unwrap();
+        std::fs::write(root.join("posts/img/used.png"), b"png").unwrap();
+        std::fs::write(root.join("posts/img/unused.png"), b"png").unwrap();
+
+        let resolver = AssetResolver::new(root);
+        let post_path = root.join("posts/a.md");
+        let mut post = processed(
+            &post_path.to_string_lossy(),
+            "<img src=\"img/used.png\" /><img src=\"img/gone.png\" />",
+        );
+        let assets = resolver.rewrite_post(&mut post);
+
+        let mut manifest = AssetManifest::new();
+        manifest.add_post(&post.file_path, assets);
+
+        assert_eq!(manifest.files().len(), 1);
+        assert!(manifest.files().contains_key("content/images/posts/img/used.png"));
+        assert_eq!(
+            manifest.by_url()["__GHOST_URL__/content/images/posts/img/used.png"],
+            root.join("posts/img/used.png")
+        );
+
+        let missing = manifest.missing_by_post();
+        assert_eq!(missing[post.file_path.as_str()].len(), 1);
+        assert_eq!(missing[post.file_path.as_str()][0].reference, "img/gone.png");
+
+        let orphans = manifest.find_orphans(root, true).unwrap();
+        assert_eq!(orphans, vec![root.join("posts/img/unused.png")]);
+        assert!(manifest.find_orphans(root, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path(Path::new("./a/./b/../c.png")), PathBuf::from("a/c.png"));
+        assert_eq!(normalize_path(Path::new("../a.png")), PathBuf::from("../a.png"));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::models::ghost::{StaffRole, User};
+use crate::models::ProcessedMarkdown;
+use crate::processors::{GhostExporter, Profile, ProfileRegistry};
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Registry files looked for in the input directory when `--authors` is not
+/// given. `../authors` matches the Tailwind Next.js starter, whose posts live
+/// in `data/blog` next to `data/authors`.
+const DISCOVERED: [&str; 5] = [
+    "authors.yaml",
+    "authors.yml",
+    "authors",
+    "data/authors",
+    "../authors",
+];
+
+/// Profile of one person, as written in the registry.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthorProfile {
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub email: Option<String>,
+    pub bio: Option<String>,
+    #[serde(alias = "avatar", alias = "image")]
+    pub profile_image: Option<String>,
+    #[serde(alias = "cover")]
+    pub cover_image: Option<String>,
+    #[serde(alias = "url")]
+    pub website: Option<String>,
+    pub location: Option<String>,
+    pub twitter: Option<String>,
+    pub facebook: Option<String>,
+    /// Staff role on the site, e.g. `editor`
+    pub role: Option<StaffRole>,
+    /// Other names posts use for this person
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(skip)]
+    pub source: PathBuf,
+}
+
+impl Profile for AuthorProfile {
+    const KIND: &'static str = "author";
+    const DISCOVERED: &'static [&'static str] = &DISCOVERED;
+
+    fn source(&self) -> &Path {
+        &self.source
+    }
+
+    fn set_source(&mut self, source: PathBuf) {
+        self.source = source;
+    }
+
+    fn names(&self) -> Vec<&String> {
+        [self.name.as_ref(), self.slug.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(&self.aliases)
+            .collect()
+    }
+
+    fn images(&mut self) -> Vec<&mut Option<String>> {
+        vec![&mut self.profile_image, &mut self.cover_image]
+    }
+
+    /// One Markdown file per author, keyed by its file name.
+    fn pages(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let mut pages = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() || !path.extension().is_some_and(|ext| ext == "md" || ext == "mdx") {
+                continue;
+            }
+            if let Some(stem) = path.file_stem() {
+                pages.push((stem.to_string_lossy().to_string(), path));
+            }
+        }
+        pages.sort();
+        Ok(pages)
+    }
+
+    fn complete(&mut self, page: ProcessedMarkdown) {
+        let frontmatter = page.frontmatter;
+        self.name = self.name.take().or(frontmatter.title);
+        self.slug = self.slug.take().or(frontmatter.slug);
+        self.profile_image = self.profile_image.take().or(frontmatter.image);
+
+        let bio = GhostExporter::html_to_plaintext(&page.html_content).replace('\n', " ");
+        if self.bio.is_none() && !bio.is_empty() {
+            self.bio = Some(bio);
+        }
+    }
+}
+
+/// Author profiles keyed by the name posts use for them.
+///
+/// Read from an `authors.yaml` mapping keys to profiles, or from a directory
+/// with one Markdown file per author whose frontmatter holds the profile and
+/// whose body is the bio (`data/authors/default.md` answers to `default`).
+pub type AuthorRegistry = ProfileRegistry<AuthorProfile>;
+
+impl AuthorRegistry {
+    /// Fills the profile fields of `user` from the profile `name` refers to.
+    pub fn apply(&self, name: &str, user: &mut User) {
+        let Some(profile) = self.get(name) else {
+            return;
+        };
+        if let Some(display_name) = &profile.name {
+            user.name = display_name.clone();
+        }
+        if let Some(email) = &profile.email {
+            user.email = email.clone();
+        }
+        user.bio = profile.bio.clone();
+        user.profile_image = profile.profile_image.clone();
+        user.cover_image = profile.cover_image.clone();
+        user.website = profile.website.clone();
+        user.location = profile.location.clone();
+        user.twitter = profile.twitter.as_deref().map(twitter_handle);
+        user.facebook = profile.facebook.as_deref().map(facebook_username);
+    }
+}
+
+/// Ghost stores Twitter accounts as `@handle`.
+fn twitter_handle(value: &str) -> String {
+    let handle = last_path_segment(value, &["twitter.com/", "x.com/"]);
+    format!("@{}", handle.trim_start_matches('@'))
+}
+
+/// Ghost stores Facebook accounts as the page or user name.
+fn facebook_username(value: &str) -> String {
+    last_path_segment(value, &["facebook.com/"]).to_string()
+}
+
+fn last_path_segment<'a>(value: &'a str, hosts: &[&str]) -> &'a str {
+    let value = value.trim();
+    hosts
+        .iter()
+        .find_map(|host| value.find(host).map(|i| &value[i + host.len()..]))
+        .map(|rest| rest.trim_end_matches('/').split(['/', '?']).next().unwrap_or(rest))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::AssetResolver;
+
+    #[test]
+    fn test_apply_author_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("authors.yaml");
+        std::fs::write(
+            &path,
+            r#"
+jane:
+  name: Jane Doe
+  email: jane@example.org
+  avatar: images/jane.png
+  twitter: https://twitter.com/janedoe
+  facebook: https://www.facebook.com/jane.doe/
+  role: editor
+  aliases: [default, "J. Doe"]
+"#,
+        )
+        .unwrap();
+
+        let registry = AuthorRegistry::discover(dir.path()).unwrap().unwrap();
+        assert_eq!(registry.key_for("Default"), Some("jane"));
+        assert_eq!(registry.key_for("jane doe"), Some("jane"));
+        assert!(registry.get("bob").is_none());
+        assert_eq!(registry.get("jane").unwrap().role, Some(StaffRole::Editor));
+
+        let mut user = User::default();
+        registry.apply("J. Doe", &mut user);
+        assert_eq!(user.name, "Jane Doe");
+        assert_eq!(user.email, "jane@example.org");
+        assert_eq!(user.twitter.as_deref(), Some("@janedoe"));
+        assert_eq!(user.facebook.as_deref(), Some("jane.doe"));
+    }
+
+    #[test]
+    fn test_load_author_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let blog = dir.path().join("data/blog");
+        let authors = dir.path().join("data/authors");
+        std::fs::create_dir_all(&blog).unwrap();
+        std::fs::create_dir_all(authors.join("img")).unwrap();
+        std::fs::write(authors.join("img/avatar.png"), b"png").unwrap();
+        std::fs::write(
+            authors.join("default.md"),
+            "---\nname: Tails Azimuth\navatar: ./img/avatar.png\noccupation: Professor\ntwitter: https://x.com/Twitter\n---\n\nA long time ago, **in a galaxy** far away.\n",
+        )
+        .unwrap();
+
+        let mut registry = AuthorRegistry::discover(&blog).unwrap().unwrap();
+        let profile = registry.get("default").unwrap();
+        assert_eq!(profile.name.as_deref(), Some("Tails Azimuth"));
+        assert_eq!(profile.bio.as_deref(), Some("A long time ago, in a galaxy far away."));
+
+        let bundled = registry.rewrite_images(&AssetResolver::new(dir.path()));
+        assert_eq!(bundled[0].0, "default");
+        assert_eq!(bundled[0].1[0].zip_path, "content/images/data/authors/img/avatar.png");
+
+        let mut user = User::default();
+        registry.apply("default", &mut user);
+        assert_eq!(
+            user.profile_image.as_deref(),
+            Some("__GHOST_URL__/content/images/data/authors/img/avatar.png")
+        );
+        assert_eq!(user.twitter.as_deref(), Some("@Twitter"));
+    }
+}
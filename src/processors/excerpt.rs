@@ -0,0 +1,177 @@
+use crate::processors::{is_han, is_kana, MarkdownProcessor};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+/// Longest `custom_excerpt` Ghost stores, in characters.
+pub const MAX_EXCERPT_LENGTH: usize = 300;
+
+/// Punctuation dropped from the end of a cut excerpt before the ellipsis.
+const TRAILING_PUNCTUATION: [char; 8] = [',', ';', ':', '-', '，', '、', '；', '：'];
+
+/// What ends an excerpt that had to be cut short.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExcerptEllipsis {
+    /// A single `…` character
+    #[default]
+    Unicode,
+    /// Three dots, `...`
+    Dots,
+    /// Nothing; the excerpt just stops
+    None,
+}
+
+impl ExcerptEllipsis {
+    fn mark(self) -> &'static str {
+        match self {
+            ExcerptEllipsis::Unicode => "…",
+            ExcerptEllipsis::Dots => "...",
+            ExcerptEllipsis::None => "",
+        }
+    }
+}
+
+/// Builds plain-text excerpts that fit Ghost's `custom_excerpt` column.
+pub struct ExcerptBuilder;
+
+impl ExcerptBuilder {
+    /// The text before a Hexo, Hugo or Jekyll `<!-- more -->` separator, or
+    /// else the first paragraph of prose. Headings, code, images and raw
+    /// HTML are left out and Markdown formatting is stripped.
+    pub fn from_markdown(markdown: &str) -> Option<String> {
+        let events: Vec<Event> = Parser::new_ext(markdown, MarkdownProcessor::parser_options()).collect();
+        let more = events.iter().position(|event| match event {
+            Event::Html(raw) | Event::InlineHtml(raw) => Self::is_more_marker(raw),
+            _ => false,
+        });
+        let excerpt = match more {
+            Some(more) => Self::prose(&events[..more], false),
+            None => Self::prose(&events, true),
+        };
+        (!excerpt.is_empty()).then_some(excerpt)
+    }
+
+    /// Cuts `text` to at most `limit` characters, ellipsis included. The cut
+    /// falls between words, or between any two Chinese or Japanese
+    /// characters, which are not separated by spaces; a single word longer
+    /// than the limit is cut where it has to be.
+    pub fn truncate(text: &str, limit: usize, ellipsis: ExcerptEllipsis) -> String {
+        let text = text.trim();
+        if text.chars().count() <= limit {
+            return text.to_string();
+        }
+        let mark = ellipsis.mark();
+        let budget = limit.saturating_sub(mark.chars().count());
+        let (cut, next) = text.char_indices().nth(budget).expect("text is longer than the limit");
+
+        let mut head = &text[..cut];
+        let at_boundary = next.is_whitespace()
+            || is_cjk(next)
+            || head.chars().last().is_some_and(|c| c.is_whitespace() || is_cjk(c));
+        if !at_boundary {
+            let last_break = head
+                .char_indices()
+                .rev()
+                .find(|(_, c)| c.is_whitespace() || is_cjk(*c));
+            if let Some((index, c)) = last_break {
+                head = if c.is_whitespace() { &head[..index] } else { &head[..index + c.len_utf8()] };
+            }
+        }
+        let head = head.trim_end_matches(|c: char| c.is_whitespace() || TRAILING_PUNCTUATION.contains(&c));
+        format!("{}{}", head, mark)
+    }
+
+    /// `<!--more-->`, spaced or not.
+    fn is_more_marker(html: &str) -> bool {
+        html.trim()
+            .strip_prefix("<!--")
+            .and_then(|comment| comment.strip_suffix("-->"))
+            .is_some_and(|comment| comment.trim().eq_ignore_ascii_case("more"))
+    }
+
+    /// Text of the paragraphs and list items in `events`, or of the first
+    /// top-level paragraph only.
+    fn prose(events: &[Event], first_paragraph: bool) -> String {
+        let mut paragraphs = Vec::new();
+        let mut text = String::new();
+        // Inside a heading, image, code block, table or footnote
+        let mut skipped = 0;
+        // Inside a quote or list
+        let mut nested = 0;
+
+        for event in events {
+            match event {
+                Event::Start(
+                    Tag::Heading { .. }
+                    | Tag::Image { .. }
+                    | Tag::CodeBlock(_)
+                    | Tag::Table(_)
+                    | Tag::FootnoteDefinition(_),
+                ) => skipped += 1,
+                Event::End(
+                    TagEnd::Heading(_)
+                    | TagEnd::Image
+                    | TagEnd::CodeBlock
+                    | TagEnd::Table
+                    | TagEnd::FootnoteDefinition,
+                ) => skipped -= 1,
+                Event::Start(Tag::BlockQuote | Tag::List(_)) => nested += 1,
+                Event::End(TagEnd::BlockQuote | TagEnd::List(_)) => nested -= 1,
+                Event::Text(t) | Event::Code(t) if skipped == 0 => text.push_str(t),
+                Event::SoftBreak | Event::HardBreak if skipped == 0 => text.push(' '),
+                Event::End(end @ (TagEnd::Paragraph | TagEnd::Item)) => {
+                    let paragraph = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    text.clear();
+                    if paragraph.is_empty() {
+                        continue;
+                    }
+                    if !first_paragraph {
+                        paragraphs.push(paragraph);
+                    } else if nested == 0 && *end == TagEnd::Paragraph {
+                        return paragraph;
+                    }
+                }
+                _ => {}
+            }
+        }
+        paragraphs.join(" ")
+    }
+}
+
+/// Characters written without spaces between words, so an excerpt can end
+/// after any of them.
+fn is_cjk(c: char) -> bool {
+    is_han(c) || is_kana(c) || matches!(c, '\u{3000}'..='\u{303F}' | '\u{FF00}'..='\u{FFEF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt_from_markdown() {
+        let markdown = "# Title\n\n![cover](a.png)\n\n> A quote\n\nThe **first** paragraph,\nwith [a link](https://x.org).\n\nSecond.\n";
+        assert_eq!(
+            ExcerptBuilder::from_markdown(markdown).as_deref(),
+            Some("The first paragraph, with a link.")
+        );
+
+        let markdown = "# Title\n\nIntro `code`.\n\n- one\n- two\n\n<!-- more -->\n\nThe rest.\n";
+        assert_eq!(ExcerptBuilder::from_markdown(markdown).as_deref(), Some("Intro code. one two"));
+
+        assert_eq!(ExcerptBuilder::from_markdown("## Only a heading\n\n```\ncode\n```\n"), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let text = "The quick brown fox jumps";
+        assert_eq!(ExcerptBuilder::truncate(text, 25, ExcerptEllipsis::Unicode), text);
+        assert_eq!(ExcerptBuilder::truncate(text, 16, ExcerptEllipsis::Unicode), "The quick brown…");
+        assert_eq!(ExcerptBuilder::truncate(text, 14, ExcerptEllipsis::Dots), "The quick...");
+        assert_eq!(ExcerptBuilder::truncate(text, 12, ExcerptEllipsis::None), "The quick");
+        assert_eq!(ExcerptBuilder::truncate("Supercalifragilistic", 6, ExcerptEllipsis::Unicode), "Super…");
+
+        let chinese = "webpack 可以看做是模块打包机：它做的事情是，分析你的项目结构";
+        assert_eq!(ExcerptBuilder::truncate(chinese, 26, ExcerptEllipsis::Unicode), "webpack 可以看做是模块打包机：它做的事情是…");
+        let long = ExcerptBuilder::truncate(&"字".repeat(400), MAX_EXCERPT_LENGTH, ExcerptEllipsis::Unicode);
+        assert_eq!(long.chars().count(), MAX_EXCERPT_LENGTH);
+    }
+}
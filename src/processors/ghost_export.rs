@@ -1,16 +1,173 @@
-use crate::models::{ghost::*, ProcessedMarkdown};
+use crate::models::{ghost::*, Frontmatter, ProcessedMarkdown, SeoFields};
+use crate::processors::{
+    AuthorRegistry, ExcerptBuilder, ExcerptEllipsis, LexicalSerializer, MobiledocSerializer,
+    PageRules, SlugKind, SlugRegistry, SlugStyle, TagRegistry, MAX_EXCERPT_LENGTH,
+};
 use anyhow::Result;
 use chrono::{DateTime, TimeZone, Utc};
+use regex::Regex;
 use std::collections::HashMap;
+use std::path::Path;
+
+/// Name of the user posts get when neither they nor `--author` name one.
+pub const DEFAULT_AUTHOR: &str = "Default Author";
 
 pub struct GhostExporter;
 
+/// Settings that shape the generated import.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Author for posts that do not name one
+    pub default_author: Option<String>,
+    /// Tags added to every post
+    pub default_tags: Vec<String>,
+    /// Storage format for post bodies
+    pub content_format: ContentFormat,
+    /// Layout of mobiledoc bodies when `content_format` is mobiledoc
+    pub mobiledoc_mode: MobiledocMode,
+    /// Ghost release whose import schema to follow. When unset it follows
+    /// the content format: mobiledoc targets 4.x, everything else 5.x.
+    pub ghost_version: Option<GhostVersion>,
+    /// How titles and names are turned into slugs
+    pub slug_style: SlugStyle,
+    /// Profiles for the authors posts name
+    pub authors: AuthorRegistry,
+    /// Staff role for users the registry and `roles` say nothing about
+    pub default_role: StaffRole,
+    /// Staff roles by lowercased author name, or registry key for authors
+    /// with a profile
+    pub roles: HashMap<String, StaffRole>,
+    /// Descriptions, images and synonyms for the tags posts use
+    pub tags: TagRegistry,
+    /// How `a/b` category paths become tags
+    pub category_hierarchy: CategoryHierarchy,
+    /// Whether a post's category or its first tag is the primary tag
+    pub primary_tag: PrimaryTag,
+    /// Internal tags added to every post, e.g. `#imported`
+    pub internal_tags: Vec<String>,
+    /// Which files are pages rather than posts
+    pub pages: PageRules,
+    /// Publish posts dated in the future right away instead of scheduling them
+    pub publish_future: bool,
+    /// Visibility of posts whose frontmatter does not set one
+    pub default_visibility: PostVisibility,
+    /// What ends excerpts cut to Ghost's 300-character limit
+    pub excerpt_ellipsis: ExcerptEllipsis,
+}
+
+/// How a category path such as `a/b` becomes tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CategoryHierarchy {
+    /// One tag per level: `a` and `b`
+    #[default]
+    Split,
+    /// One tag for the whole path: `a-b`
+    Join,
+}
+
+/// Which of a post's tags Ghost treats as the primary one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PrimaryTag {
+    /// The first category, followed by the tags
+    #[default]
+    Category,
+    /// The first tag, followed by the categories
+    Tag,
+}
+
+/// Spellings of one tag or author that were imported as a single record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameMerge {
+    pub kind: SlugKind,
+    /// Name the record was imported under
+    pub canonical: String,
+    /// The other spellings posts used for it
+    pub merged: Vec<String>,
+}
+
+/// Spellings posts use for each tag or author, by normalised key.
+#[derive(Debug, Default)]
+struct Spellings {
+    /// Key -> spellings with how often each is used, in order of first use
+    names: HashMap<String, Vec<(String, usize)>>,
+    /// Keys in order of first use
+    keys: Vec<String>,
+}
+
+impl Spellings {
+    fn add(&mut self, key: String, name: &str) {
+        let names = self.names.entry(key).or_insert_with_key(|key| {
+            self.keys.push(key.clone());
+            Vec::new()
+        });
+        match names.iter_mut().find(|(spelling, _)| spelling == name) {
+            Some((_, count)) => *count += 1,
+            None => names.push((name.to_string(), 1)),
+        }
+    }
+
+    fn names(&self, key: &str) -> impl Iterator<Item = &str> {
+        self.names.get(key).into_iter().flatten().map(|(name, _)| name.as_str())
+    }
+
+    /// The most used spelling, the earliest one on a tie.
+    fn canonical(&self, key: &str) -> &str {
+        let names = &self.names[key];
+        let best = names
+            .iter()
+            .fold(&names[0], |best, name| if name.1 > best.1 { name } else { best });
+        &best.0
+    }
+
+    /// The merge of `key`'s spellings into `display_name`, or the canonical
+    /// spelling when the registry does not name the record.
+    fn merge(&self, kind: SlugKind, key: &str, display_name: Option<String>) -> Option<NameMerge> {
+        let canonical = display_name.unwrap_or_else(|| self.canonical(key).to_string());
+        let merged: Vec<String> = self
+            .names(key)
+            .filter(|name| *name != canonical)
+            .map(str::to_string)
+            .collect();
+        (!merged.is_empty()).then_some(NameMerge { kind, canonical, merged })
+    }
+}
+
+impl ExportOptions {
+    pub fn target_version(&self) -> GhostVersion {
+        self.ghost_version.unwrap_or(match self.content_format {
+            ContentFormat::Mobiledoc => GhostVersion::V4,
+            _ => GhostVersion::V5,
+        })
+    }
+}
+
 impl GhostExporter {
     pub fn create_export(
         posts: Vec<ProcessedMarkdown>,
         default_author: Option<&str>,
         default_tags: Vec<String>,
     ) -> Result<GhostImport> {
+        let options = ExportOptions {
+            default_author: default_author.map(str::to_string),
+            default_tags,
+            ..Default::default()
+        };
+        Self::create_export_with_options(posts, &options)
+    }
+
+    pub fn create_export_with_options(
+        posts: Vec<ProcessedMarkdown>,
+        options: &ExportOptions,
+    ) -> Result<GhostImport> {
+        let version = options.target_version();
+        match options.content_format {
+            ContentFormat::Html => {}
+            ContentFormat::Lexical => version.ensure(SchemaFeature::LexicalContent)?,
+            ContentFormat::Mobiledoc => version.ensure(SchemaFeature::MobiledocContent)?,
+        }
+
+        let default_author = options.default_author.as_deref();
+        let (tag_names, user_names) = Self::spellings(&posts, options);
         let mut data = Data {
             posts: Vec::new(),
             tags: Vec::new(),
@@ -18,142 +175,202 @@ impl GhostExporter {
             posts_tags: Vec::new(),
             posts_authors: Vec::new(),
             roles_users: Vec::new(),
+            roles: Vec::new(),
+            products: Vec::new(),
+            posts_products: Vec::new(),
+            posts_meta: Vec::new(),
         };
 
         let mut tag_map: HashMap<String, i32> = HashMap::new();
         let mut user_map: HashMap<String, i32> = HashMap::new();
+        let mut tier_map: HashMap<String, i32> = HashMap::new();
+        let mut user_roles: Vec<(i32, StaffRole)> = Vec::new();
+        let mut slugs = SlugRegistry::new(options.slug_style);
         let mut next_id = 1;
 
+        // Slugs set in frontmatter are claimed first so generated ones yield to them
+        let post_slugs: Vec<Option<String>> = posts
+            .iter()
+            .map(|p| p.frontmatter.slug.as_ref().map(|s| slugs.reserve(SlugKind::Post, s)))
+            .collect();
+
         // Create default user if provided
         if let Some(author_name) = default_author {
-            let user = User {
-                id: next_id,
-                name: author_name.to_string(),
-                slug: Self::generate_slug(author_name),
-                email: format!("{}@example.com", Self::generate_slug(author_name)),
-                ..Default::default()
-            };
-            user_map.insert(author_name.to_string(), next_id);
-            data.users.push(user);
+            let key = Self::author_key(author_name, options);
+            data.users.push(Self::create_user(next_id, &key, &user_names, &options.authors, &mut slugs));
+            user_roles.push((next_id, Self::staff_role(&key, &user_names, options)));
+            user_map.insert(key, next_id);
             next_id += 1;
         }
 
         // Process default tags
-        for tag_name in default_tags {
-            if !tag_map.contains_key(&tag_name) {
-                let tag = Tag {
-                    id: next_id,
-                    name: tag_name.clone(),
-                    slug: Self::generate_slug(&tag_name),
-                    ..Default::default()
-                };
-                tag_map.insert(tag_name, next_id);
-                data.tags.push(tag);
+        for tag_name in &options.default_tags {
+            let key = Self::tag_key(tag_name, options);
+            if let std::collections::hash_map::Entry::Vacant(entry) = tag_map.entry(key) {
+                data.tags.push(Self::create_tag(next_id, entry.key(), &tag_names, options, &mut slugs));
+                entry.insert(next_id);
                 next_id += 1;
             }
         }
 
         // Process each markdown file
-        for (index, processed) in posts.into_iter().enumerate() {
+        for ((index, processed), slug) in posts.into_iter().enumerate().zip(post_slugs) {
             let post_id = next_id;
             next_id += 1;
+            let is_page = options.pages.is_page(&processed);
+            let tags = Self::post_tags(&processed.frontmatter, is_page, options);
+            let authors = Self::post_authors(&processed.frontmatter, default_author);
+
+            // Parse date
+            let now = Utc::now();
+            let published_at = match &processed.frontmatter.date {
+                Some(date_str) => Self::parse_date(date_str).unwrap_or(now),
+                None => now,
+            };
+            let status = Self::post_status(&processed, published_at > now, options)?;
+            let (visibility, tiers) = Self::post_visibility(&processed, options)?;
+            let seo = processed.frontmatter.seo();
+            let excerpt = Self::post_excerpt(&processed, options);
 
             // Create post
             let mut post = Post::default();
             post.id = post_id;
+            post.source_path = Some(processed.file_path.clone());
             post.title = processed.frontmatter.title
                 .unwrap_or_else(|| format!("Untitled Post {}", index + 1));
-            post.slug = processed.frontmatter.slug
-                .unwrap_or_else(|| Self::generate_slug(&post.title));
+            post.slug = slug.unwrap_or_else(|| slugs.claim(SlugKind::Post, &post.title));
+            if is_page {
+                post.r#type = "page".to_string();
+            }
+            match options.content_format {
+                ContentFormat::Html => {}
+                ContentFormat::Lexical => {
+                    let serializer = LexicalSerializer::new(&processed.asset_urls);
+                    post.lexical = Some(serializer.to_json_string(&processed.content));
+                }
+                ContentFormat::Mobiledoc => {
+                    let serializer = MobiledocSerializer::new(&processed.asset_urls);
+                    post.mobiledoc = Some(
+                        serializer.to_json_string(&processed.content, options.mobiledoc_mode),
+                    );
+                }
+            }
+            if version.has_plaintext() {
+                post.plaintext = Some(Self::html_to_plaintext(&processed.html_content));
+            }
             post.html = Some(processed.html_content);
             
             // Handle image from multiple possible fields
             post.feature_image = processed.frontmatter.image
                 .or_else(|| processed.frontmatter.images.as_ref().and_then(|imgs| imgs.first().cloned()));
             
-            // Handle draft status and featured
-            let is_draft = processed.frontmatter.draft.unwrap_or(false);
+            // Handle status and featured
             post.featured = Some(if processed.frontmatter.featured.unwrap_or(false) { 1 } else { 0 });
-            post.status = if is_draft {
-                "draft".to_string()
-            } else {
-                processed.frontmatter.status.unwrap_or_else(|| "published".to_string())
-            };
-
-            // Parse date
-            if let Some(date_str) = &processed.frontmatter.date {
-                post.published_at = Some(Self::parse_date(date_str).unwrap_or_else(|_| Utc::now()));
-            } else {
-                post.published_at = Some(Utc::now());
-            }
+            post.status = status.name().to_string();
+            post.visibility = visibility.name().to_string();
+            post.published_at = Some(published_at);
 
             // Set created/updated times
-            let now = Utc::now();
             post.created_at = now;
             post.updated_at = now;
             
-            // Handle description/summary
-            post.custom_excerpt = processed.frontmatter.description
-                .or_else(|| processed.frontmatter.summary.clone());
+            post.custom_excerpt = excerpt;
+
+            post.canonical_url = seo.canonical_url.clone();
+            if version.has_posts_meta() {
+                let mut meta = Self::posts_meta(next_id, &post, seo, &processed.asset_urls);
+                if !version.has_feature_image_caption() {
+                    meta.feature_image_alt = None;
+                    meta.feature_image_caption = None;
+                }
+                data.posts_meta.push(meta);
+                next_id += 1;
+            } else {
+                post.meta_title = seo.meta_title;
+                post.meta_description = seo.meta_description;
+            }
 
             data.posts.push(post);
 
-            // Process tags
-            if let Some(tags) = &processed.frontmatter.tags {
-                for tag_name in tags {
-                    let tag_id = *tag_map.entry(tag_name.clone()).or_insert_with(|| {
-                        let tag = Tag {
-                            id: next_id,
-                            name: tag_name.clone(),
-                            slug: Self::generate_slug(tag_name),
-                            ..Default::default()
-                        };
-                        data.tags.push(tag);
-                        let id = next_id;
-                        next_id += 1;
-                        id
-                    });
-
-                    data.posts_tags.push(PostsTags {
-                        id: next_id,
-                        post_id,
-                        tag_id,
-                    });
+            // Process tags; the first one is the primary tag
+            let mut tag_ids: Vec<i32> = Vec::new();
+            for tag_name in &tags {
+                let key = Self::tag_key(tag_name, options);
+                let tag_id = *tag_map.entry(key.clone()).or_insert_with(|| {
+                    data.tags.push(Self::create_tag(next_id, &key, &tag_names, options, &mut slugs));
+                    let id = next_id;
                     next_id += 1;
+                    id
+                });
+                // Spellings of one tag listed together attach it once
+                if tag_ids.contains(&tag_id) {
+                    continue;
                 }
+
+                data.posts_tags.push(PostsTags {
+                    id: next_id,
+                    post_id,
+                    tag_id,
+                    sort_order: tag_ids.len() as i32,
+                });
+                tag_ids.push(tag_id);
+                next_id += 1;
             }
 
-            // Process author/authors
-            let authors = if let Some(authors_list) = &processed.frontmatter.authors {
-                authors_list.clone()
-            } else if let Some(author_name) = &processed.frontmatter.author {
-                vec![author_name.clone()]
-            } else if let Some(default_author_name) = default_author {
-                vec![default_author_name.to_string()]
-            } else {
-                vec!["Default Author".to_string()]
-            };
+            // Tiers are matched to the site's own by slug on import
+            let mut tier_ids: Vec<i32> = Vec::new();
+            for tier_name in &tiers {
+                let key = Self::name_key(tier_name, options.slug_style);
+                let product_id = *tier_map.entry(key).or_insert_with(|| {
+                    let slug = slugs.claim(SlugKind::Tier, tier_name);
+                    data.products.push(Product::new(next_id, tier_name.trim(), slug));
+                    let id = next_id;
+                    next_id += 1;
+                    id
+                });
+                if tier_ids.contains(&product_id) {
+                    continue;
+                }
 
+                data.posts_products.push(PostsProducts {
+                    id: next_id,
+                    post_id,
+                    product_id,
+                    sort_order: tier_ids.len() as i32,
+                });
+                tier_ids.push(product_id);
+                next_id += 1;
+            }
+
+            // Process author/authors
+            let mut author_ids: Vec<i32> = Vec::new();
             for author_name in authors {
-                let author_id = *user_map.entry(author_name.clone()).or_insert_with(|| {
-                    let user = User {
-                        id: next_id,
-                        name: author_name.clone(),
-                        slug: Self::generate_slug(&author_name),
-                        email: format!("{}@example.com", Self::generate_slug(&author_name)),
-                        ..Default::default()
-                    };
+                let key = Self::author_key(&author_name, options);
+                let author_id = *user_map.entry(key.clone()).or_insert_with(|| {
+                    let user = Self::create_user(next_id, &key, &user_names, &options.authors, &mut slugs);
+                    user_roles.push((next_id, Self::staff_role(&key, &user_names, options)));
                     data.users.push(user);
                     let id = next_id;
                     next_id += 1;
                     id
                 });
+                if author_ids.contains(&author_id) {
+                    continue;
+                }
+
+                if author_ids.is_empty() && version.has_post_author_id() {
+                    if let Some(post) = data.posts.last_mut() {
+                        post.author_id = Some(author_id);
+                    }
+                }
 
                 data.posts_authors.push(PostsAuthors {
                     id: next_id,
                     post_id,
                     author_id,
+                    sort_order: author_ids.len() as i32,
                 });
+                author_ids.push(author_id);
                 next_id += 1;
             }
         }
@@ -162,14 +379,29 @@ impl GhostExporter {
         if data.users.is_empty() {
             let user = User {
                 id: 1,
-                name: "Default Author".to_string(),
+                name: DEFAULT_AUTHOR.to_string(),
                 slug: "default-author".to_string(),
                 email: "author@example.com".to_string(),
                 ..Default::default()
             };
+            user_roles.push((user.id, options.default_role));
             data.users.push(user);
         }
 
+        let mut role_ids: HashMap<StaffRole, i32> = HashMap::new();
+        for (user_id, role) in user_roles {
+            let role_id = *role_ids.entry(role).or_insert_with(|| {
+                let id = data.roles.len() as i32 + 1;
+                data.roles.push(Role::new(id, role));
+                id
+            });
+            data.roles_users.push(RolesUsers {
+                id: data.roles_users.len() as i32 + 1,
+                user_id,
+                role_id,
+            });
+        }
+
         // Ensure at least one tag exists
         if data.tags.is_empty() {
             let tag = Tag {
@@ -182,19 +414,322 @@ impl GhostExporter {
         }
 
         Ok(GhostImport {
-            meta: Meta::default(),
+            meta: Meta::for_version(version),
             data,
         })
     }
 
-    fn generate_slug(text: &str) -> String {
-        text.to_lowercase()
-            .chars()
-            .map(|c| if c.is_alphanumeric() || c == ' ' { c } else { ' ' })
-            .collect::<String>()
-            .split_whitespace()
-            .collect::<Vec<&str>>()
-            .join("-")
+    /// Tags and authors whose spellings were folded into one record, so
+    /// callers can tell users which names were merged.
+    pub fn name_merges(posts: &[ProcessedMarkdown], options: &ExportOptions) -> Vec<NameMerge> {
+        let (tags, users) = Self::spellings(posts, options);
+        let tag_name = |key: &str| tags.names(key).find_map(|n| options.tags.get(n)?.name.clone());
+        let user_name = |key: &str| users.names(key).find_map(|n| options.authors.get(n)?.name.clone());
+
+        let mut merges = Vec::new();
+        for key in &tags.keys {
+            merges.extend(tags.merge(SlugKind::Tag, key, tag_name(key)));
+        }
+        for key in &users.keys {
+            merges.extend(users.merge(SlugKind::User, key, user_name(key)));
+        }
+        merges
+    }
+
+    /// Every spelling of the tags and authors the posts use, in the order
+    /// the export meets them.
+    fn spellings(posts: &[ProcessedMarkdown], options: &ExportOptions) -> (Spellings, Spellings) {
+        let mut tags = Spellings::default();
+        let mut users = Spellings::default();
+        if let Some(author) = &options.default_author {
+            users.add(Self::author_key(author, options), author);
+        }
+        for tag in &options.default_tags {
+            tags.add(Self::tag_key(tag, options), tag);
+        }
+        for processed in posts {
+            let is_page = options.pages.is_page(processed);
+            for tag in Self::post_tags(&processed.frontmatter, is_page, options) {
+                tags.add(Self::tag_key(&tag, options), &tag);
+            }
+            for author in Self::post_authors(&processed.frontmatter, options.default_author.as_deref()) {
+                users.add(Self::author_key(&author, options), &author);
+            }
+        }
+        (tags, users)
+    }
+
+    /// Tag names of a post in `posts_tags` order, categories included.
+    /// Pages keep the tags they name but not categories, which only mean
+    /// something in the blog's own listings.
+    fn post_tags(frontmatter: &Frontmatter, is_page: bool, options: &ExportOptions) -> Vec<String> {
+        let categories = if is_page { Vec::new() } else { frontmatter.categories() };
+        let categories = categories.into_iter().flat_map(|category| {
+            let path: Vec<&str> = category.split('/').map(str::trim).filter(|p| !p.is_empty()).collect();
+            if path.is_empty() {
+                return Vec::new();
+            }
+            match options.category_hierarchy {
+                CategoryHierarchy::Split => path.iter().map(|p| p.to_string()).collect(),
+                CategoryHierarchy::Join => vec![path.join("-")],
+            }
+        });
+        let tags = frontmatter.tags.iter().flatten().cloned();
+        let mut names: Vec<String> = match options.primary_tag {
+            PrimaryTag::Category => categories.chain(tags).collect(),
+            PrimaryTag::Tag => tags.chain(categories).collect(),
+        };
+
+        // Internal tags go last so they never become the primary tag
+        let internal = frontmatter.internal_tags.iter().flatten().chain(&options.internal_tags);
+        names.extend(internal.map(|name| Self::internal_tag_name(name)));
+        names
+    }
+
+    /// `imported` and `#imported` both name the internal tag `#imported`.
+    fn internal_tag_name(name: &str) -> String {
+        let name = name.trim();
+        if Tag::is_internal(name) {
+            name.to_string()
+        } else {
+            format!("{}{}", Tag::INTERNAL_PREFIX, name)
+        }
+    }
+
+    fn post_authors(frontmatter: &Frontmatter, default_author: Option<&str>) -> Vec<String> {
+        if let Some(authors_list) = &frontmatter.authors {
+            authors_list.clone()
+        } else if let Some(author_name) = &frontmatter.author {
+            vec![author_name.clone()]
+        } else if let Some(default_author_name) = default_author {
+            vec![default_author_name.to_string()]
+        } else {
+            vec![DEFAULT_AUTHOR.to_string()]
+        }
+    }
+
+    /// Tags that slugify alike, or that the registry lists as synonyms, are
+    /// one tag.
+    fn tag_key(name: &str, options: &ExportOptions) -> String {
+        let name = options.tags.key_for(name).unwrap_or(name);
+        match name.strip_prefix(Tag::INTERNAL_PREFIX) {
+            Some(name) => format!("hash-{}", Self::name_key(name, options.slug_style)),
+            None => Self::name_key(name, options.slug_style),
+        }
+    }
+
+    /// Authors that slugify alike, or that name the same registry profile,
+    /// are one user.
+    fn author_key(name: &str, options: &ExportOptions) -> String {
+        Self::name_key(options.authors.key_for(name).unwrap_or(name), options.slug_style)
+    }
+
+    fn name_key(name: &str, style: SlugStyle) -> String {
+        let slug = style.slugify(name);
+        if slug.is_empty() {
+            name.trim().to_lowercase()
+        } else {
+            slug
+        }
+    }
+
+    /// Role from `--role`, then the author registry, then the default.
+    fn staff_role(key: &str, users: &Spellings, options: &ExportOptions) -> StaffRole {
+        let role_key = |name: &str| options.authors.key_for(name).unwrap_or(name).trim().to_lowercase();
+        users
+            .names(key)
+            .find_map(|name| options.roles.get(&role_key(name)).copied())
+            .or_else(|| users.names(key).find_map(|name| options.authors.get(name)?.role))
+            .unwrap_or(options.default_role)
+    }
+
+    fn create_tag(
+        id: i32,
+        key: &str,
+        tags: &Spellings,
+        options: &ExportOptions,
+        slugs: &mut SlugRegistry,
+    ) -> Tag {
+        let name = tags.canonical(key);
+        let profile = tags.names(key).find(|n| options.tags.get(n).is_some()).unwrap_or(name);
+        let mut tag = Tag {
+            id,
+            name: name.to_string(),
+            ..Default::default()
+        };
+        options.tags.apply(profile, &mut tag);
+        if !options.target_version().has_tag_accent_color() {
+            tag.accent_color = None;
+        }
+        // Ghost spells the slug of the internal tag `#name` as `hash-name`
+        let name = match tag.name.strip_prefix(Tag::INTERNAL_PREFIX) {
+            Some(name) => {
+                tag.visibility = "internal".to_string();
+                format!("hash-{}", name)
+            }
+            None => tag.name.clone(),
+        };
+        tag.slug = match options.tags.get(profile).and_then(|profile| profile.slug.as_deref()) {
+            Some(slug) => slugs.reserve(SlugKind::Tag, slug),
+            None => slugs.claim(SlugKind::Tag, &name),
+        };
+        tag
+    }
+
+    fn create_user(
+        id: i32,
+        key: &str,
+        users: &Spellings,
+        authors: &AuthorRegistry,
+        slugs: &mut SlugRegistry,
+    ) -> User {
+        let name = users.canonical(key);
+        let profile = users.names(key).find(|n| authors.get(n).is_some()).unwrap_or(name);
+        let mut user = User {
+            id,
+            name: name.to_string(),
+            ..Default::default()
+        };
+        authors.apply(profile, &mut user);
+        user.slug = match authors.get(profile).and_then(|profile| profile.slug.as_deref()) {
+            Some(slug) => slugs.reserve(SlugKind::User, slug),
+            None => slugs.claim(SlugKind::User, &user.name),
+        };
+        if user.email.is_empty() {
+            user.email = format!("{}@example.com", user.slug);
+        }
+        user
+    }
+
+    pub(crate) fn html_to_plaintext(html: &str) -> String {
+        let tags = Regex::new(r"(?s)<[^>]*>").unwrap();
+        let text = tags.replace_all(html, "");
+        let text = text
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&");
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The `posts_meta` row of `post`. Social fields the frontmatter leaves
+    /// out fall back to the SEO fields, then to the title, excerpt and
+    /// feature image, and Twitter falls back to Open Graph.
+    fn posts_meta(
+        id: i32,
+        post: &Post,
+        seo: SeoFields,
+        asset_urls: &HashMap<String, String>,
+    ) -> PostsMeta {
+        let image = |src: Option<String>| src.map(|src| asset_urls.get(&src).cloned().unwrap_or(src));
+        let og_title = seo.og_title.or_else(|| seo.meta_title.clone()).or_else(|| Some(post.title.clone()));
+        let og_description = seo
+            .og_description
+            .or_else(|| seo.meta_description.clone())
+            .or_else(|| post.custom_excerpt.clone());
+        let og_image = image(seo.og_image).or_else(|| post.feature_image.clone());
+        PostsMeta {
+            id,
+            post_id: post.id,
+            meta_title: seo.meta_title,
+            meta_description: seo.meta_description,
+            twitter_image: image(seo.twitter_image).or_else(|| og_image.clone()),
+            twitter_title: seo.twitter_title.or_else(|| og_title.clone()),
+            twitter_description: seo.twitter_description.or_else(|| og_description.clone()),
+            og_image,
+            og_title,
+            og_description,
+            email_subject: seo.email_subject,
+            feature_image_alt: seo.feature_image_alt,
+            feature_image_caption: seo.feature_image_caption,
+        }
+    }
+
+    /// Draft markers win over `status`: `draft: true`, Jekyll's
+    /// `published: false` and files in a Jekyll or Hexo `_drafts` directory.
+    /// A published post dated in the future is scheduled for that date, and
+    /// a post can only be `scheduled` when its date is in the future.
+    fn post_status(processed: &ProcessedMarkdown, in_future: bool, options: &ExportOptions) -> Result<PostStatus> {
+        let frontmatter = &processed.frontmatter;
+        let unpublished = frontmatter.extra.get("published").and_then(|v| v.as_bool()) == Some(false);
+        let in_drafts = Path::new(&processed.file_path)
+            .components()
+            .any(|component| component.as_os_str() == "_drafts");
+        if frontmatter.draft.unwrap_or(false) || unpublished || in_drafts {
+            return Ok(PostStatus::Draft);
+        }
+
+        let status = match &frontmatter.status {
+            Some(status) => PostStatus::try_from(status.clone())
+                .map_err(|e| anyhow::anyhow!("{}: {}", processed.file_path, e))?,
+            None => PostStatus::Published,
+        };
+        Ok(match status {
+            PostStatus::Published if in_future && !options.publish_future => PostStatus::Scheduled,
+            PostStatus::Scheduled if !in_future => anyhow::bail!(
+                "{}: status \"scheduled\" needs a date in the future",
+                processed.file_path
+            ),
+            status => status,
+        })
+    }
+
+    /// `description` or `summary` from frontmatter, or else an excerpt of the
+    /// body, cut to fit Ghost's limit.
+    fn post_excerpt(processed: &ProcessedMarkdown, options: &ExportOptions) -> Option<String> {
+        let frontmatter = &processed.frontmatter;
+        frontmatter
+            .description
+            .clone()
+            .or_else(|| frontmatter.summary.clone())
+            .filter(|excerpt| !excerpt.trim().is_empty())
+            .or_else(|| ExcerptBuilder::from_markdown(&processed.content))
+            .map(|excerpt| ExcerptBuilder::truncate(&excerpt, MAX_EXCERPT_LENGTH, options.excerpt_ellipsis))
+    }
+
+    /// `visibility` from frontmatter, or the default when it has none. A
+    /// `tiers` list on its own makes the post one for those tiers.
+    fn post_visibility(
+        processed: &ProcessedMarkdown,
+        options: &ExportOptions,
+    ) -> Result<(PostVisibility, Vec<String>)> {
+        let frontmatter = &processed.frontmatter;
+        let error = |message: String| anyhow::anyhow!("{}: {}", processed.file_path, message);
+        let tiers = frontmatter.tiers();
+        let visibility = match &frontmatter.visibility {
+            Some(visibility) => PostVisibility::try_from(visibility.clone()).map_err(error)?,
+            None if !tiers.is_empty() => PostVisibility::Tiers,
+            None => options.default_visibility,
+        };
+
+        let feature = match visibility {
+            PostVisibility::Public => None,
+            PostVisibility::Members | PostVisibility::Paid => Some(SchemaFeature::MembersVisibility),
+            PostVisibility::Tiers => Some(SchemaFeature::TierVisibility),
+        };
+        if let Some(feature) = feature {
+            options
+                .target_version()
+                .ensure(feature)
+                .map_err(|e| error(e.to_string()))?;
+        }
+
+        match visibility {
+            PostVisibility::Tiers if tiers.is_empty() => Err(error(
+                "visibility \"tiers\" needs a tiers list naming who can read the post".to_string(),
+            )),
+            PostVisibility::Tiers => Ok((visibility, tiers)),
+            _ if !tiers.is_empty() => Err(error(format!(
+                "tiers only apply to visibility \"tiers\", not {:?}",
+                visibility.name()
+            ))),
+            _ => Ok((visibility, Vec::new())),
+        }
     }
 
     fn parse_date(date_str: &str) -> Result<DateTime<Utc>> {
@@ -242,7 +777,7 @@ impl GhostExporter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::TimeZone;
+    use chrono::{Datelike, Timelike};
 
     #[test]
     fn test_create_export() {
@@ -260,6 +795,7 @@ mod tests {
             html_content: "<h1>Hello World</h1>".to_string(),
             file_path: "test.md".to_string(),
             images: vec![],
+            ..Default::default()
         };
 
         let export = GhostExporter::create_export(
@@ -275,10 +811,467 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_slug() {
-        assert_eq!(GhostExporter::generate_slug("Hello World"), "hello-world");
-        assert_eq!(GhostExporter::generate_slug("Test's Post!"), "test-s-post");
-        assert_eq!(GhostExporter::generate_slug("Multiple   Spaces"), "multiple-spaces");
+    fn test_lexical_content_format() {
+        let processed = ProcessedMarkdown {
+            frontmatter: crate::models::Frontmatter {
+                title: Some("Lexical".to_string()),
+                ..Default::default()
+            },
+            content: "Hello **world**".to_string(),
+            html_content: "<p>Hello <strong>world</strong></p>".to_string(),
+            file_path: "lexical.md".to_string(),
+            ..Default::default()
+        };
+        let options = ExportOptions {
+            content_format: ContentFormat::Lexical,
+            ..Default::default()
+        };
+
+        let export = GhostExporter::create_export_with_options(vec![processed], &options).unwrap();
+        let post = &export.data.posts[0];
+        let lexical: serde_json::Value =
+            serde_json::from_str(post.lexical.as_deref().unwrap()).unwrap();
+
+        assert_eq!(lexical["root"]["children"][0]["type"], "paragraph");
+        assert!(post.html.is_some());
+    }
+
+    #[test]
+    fn test_mobiledoc_content_format() {
+        let processed = ProcessedMarkdown {
+            content: "Hello **world**".to_string(),
+            html_content: "<p>Hello <strong>world</strong></p>".to_string(),
+            file_path: "mobiledoc.md".to_string(),
+            ..Default::default()
+        };
+        let options = ExportOptions {
+            content_format: ContentFormat::Mobiledoc,
+            ..Default::default()
+        };
+
+        let export = GhostExporter::create_export_with_options(vec![processed], &options).unwrap();
+        let mobiledoc: serde_json::Value =
+            serde_json::from_str(export.data.posts[0].mobiledoc.as_deref().unwrap()).unwrap();
+
+        assert_eq!(mobiledoc["cards"][0][1]["markdown"], "Hello **world**");
+        assert!(export.data.posts[0].lexical.is_none());
+        assert_eq!(export.meta.version, "4.48.9");
+    }
+
+    #[test]
+    fn test_ghost_version_schema() {
+        let processed = || ProcessedMarkdown {
+            frontmatter: crate::models::Frontmatter {
+                author: Some("Jane".to_string()),
+                ..Default::default()
+            },
+            content: "Hello".to_string(),
+            html_content: "<p>Hello &amp; bye</p>\n".to_string(),
+            file_path: "schema.md".to_string(),
+            ..Default::default()
+        };
+
+        let options = ExportOptions {
+            ghost_version: Some(GhostVersion::V3),
+            content_format: ContentFormat::Mobiledoc,
+            ..Default::default()
+        };
+        let export = GhostExporter::create_export_with_options(vec![processed()], &options).unwrap();
+        let post = &export.data.posts[0];
+        assert_eq!(export.meta.version, "3.42.9");
+        assert_eq!(post.plaintext.as_deref(), Some("Hello & bye"));
+        assert_eq!(post.author_id, Some(export.data.users[0].id));
+
+        let export =
+            GhostExporter::create_export_with_options(vec![processed()], &ExportOptions::default())
+                .unwrap();
+        assert!(export.data.posts[0].plaintext.is_none());
+        assert!(export.data.posts[0].author_id.is_none());
+
+        let options = ExportOptions {
+            ghost_version: Some(GhostVersion::V4),
+            content_format: ContentFormat::Lexical,
+            ..Default::default()
+        };
+        assert!(GhostExporter::create_export_with_options(vec![processed()], &options).is_err());
+    }
+
+    #[test]
+    fn test_unique_slugs() {
+        let post = |title: &str, slug: Option<&str>| ProcessedMarkdown {
+            frontmatter: crate::models::Frontmatter {
+                title: Some(title.to_string()),
+                slug: slug.map(str::to_string),
+                author: Some("Zoë".to_string()),
+                tags: Some(vec!["Rust".to_string(), "rust".to_string()]),
+                ..Default::default()
+            },
+            file_path: format!("{}.md", title),
+            ..Default::default()
+        };
+        let posts = vec![post("Notes", None), post("Notes", None), post("Other", Some("notes"))];
+
+        let export = GhostExporter::create_export(posts, None, vec![]).unwrap();
+        let slugs: Vec<_> = export.data.posts.iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["notes-2", "notes-3", "notes"]);
+        let tags: Vec<_> = export.data.tags.iter().map(|t| t.slug.as_str()).collect();
+        assert_eq!(tags, vec!["rust"]);
+
+        let options = ExportOptions {
+            slug_style: SlugStyle::Ascii,
+            ..Default::default()
+        };
+        let posts = vec![post("Café", None), post("Old", Some("my_old_url")), post("Diary", Some("日記"))];
+        let export = GhostExporter::create_export_with_options(posts, &options).unwrap();
+        let slugs: Vec<_> = export.data.posts.iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["cafe", "my_old_url", "日記"]);
+        assert_eq!(export.data.users[0].email, "zoe@example.com");
+    }
+
+    #[test]
+    fn test_author_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("authors.yaml");
+        std::fs::write(
+            &path,
+            "jane:\n  name: Jane Doe\n  email: jane@example.org\n  role: Editor\n  aliases: [default]\n",
+        )
+        .unwrap();
+        let post = |author: &str| ProcessedMarkdown {
+            frontmatter: crate::models::Frontmatter {
+                authors: Some(vec![author.to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let options = ExportOptions {
+            authors: AuthorRegistry::load(&path).unwrap(),
+            roles: HashMap::from([("bob".to_string(), StaffRole::Administrator)]),
+            default_role: StaffRole::Contributor,
+            ..Default::default()
+        };
+
+        let export = GhostExporter::create_export_with_options(
+            vec![post("default"), post("Jane Doe"), post("Bob")],
+            &options,
+        )
+        .unwrap();
+        let users: Vec<_> = export.data.users.iter().map(|u| (u.slug.as_str(), u.email.as_str())).collect();
+        assert_eq!(users, vec![("jane-doe", "jane@example.org"), ("bob", "bob@example.com")]);
+        assert_eq!(export.data.posts_authors[0].author_id, export.data.posts_authors[1].author_id);
+
+        let roles: Vec<_> = export
+            .data
+            .roles_users
+            .iter()
+            .map(|r| export.data.roles[r.role_id as usize - 1].name.as_str())
+            .collect();
+        assert_eq!(roles, vec!["Editor", "Administrator"]);
+    }
+
+    #[test]
+    fn test_tag_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tags.yaml");
+        std::fs::write(
+            &path,
+            "vue:\n  name: Vue.js\n  description: Components\n  color: 42b883\n  synonyms: [VUE]\n",
+        )
+        .unwrap();
+        let post = |tags: &[&str]| ProcessedMarkdown {
+            frontmatter: crate::models::Frontmatter {
+                tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut options = ExportOptions {
+            default_tags: vec!["vue".to_string()],
+            tags: TagRegistry::load(&path).unwrap(),
+            ..Default::default()
+        };
+
+        let export = GhostExporter::create_export_with_options(
+            vec![post(&["VUE", "Rust"]), post(&["Vue.js"])],
+            &options,
+        )
+        .unwrap();
+        let tags: Vec<_> = export.data.tags.iter().map(|t| (t.name.as_str(), t.slug.as_str())).collect();
+        assert_eq!(tags, vec![("Vue.js", "vue-js"), ("Rust", "rust")]);
+        assert_eq!(export.data.tags[0].accent_color.as_deref(), Some("#42b883"));
+        assert_eq!(export.data.tags[0].description.as_deref(), Some("Components"));
+        assert_eq!(export.data.posts_tags.iter().filter(|r| r.tag_id == export.data.tags[0].id).count(), 2);
+
+        options.ghost_version = Some(GhostVersion::V3);
+        let export = GhostExporter::create_export_with_options(vec![post(&["vue"])], &options).unwrap();
+        assert_eq!(export.data.tags[0].accent_color, None);
+    }
+
+    #[test]
+    fn test_case_insensitive_names() {
+        let post = |tags: &[&str], author: &str| ProcessedMarkdown {
+            frontmatter: crate::models::Frontmatter {
+                tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+                author: Some(author.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let posts = vec![
+            post(&["VUE", "Rust"], "jane doe"),
+            post(&["Vue", "vue"], "Jane Doe"),
+            post(&["Vue"], "Jane Doe"),
+        ];
+        let options = ExportOptions::default();
+
+        let merges = GhostExporter::name_merges(&posts, &options);
+        assert_eq!(
+            merges,
+            vec![
+                NameMerge { kind: SlugKind::Tag, canonical: "Vue".into(), merged: vec!["VUE".into(), "vue".into()] },
+                NameMerge { kind: SlugKind::User, canonical: "Jane Doe".into(), merged: vec!["jane doe".into()] },
+            ]
+        );
+
+        let export = GhostExporter::create_export_with_options(posts, &options).unwrap();
+        let tags: Vec<_> = export.data.tags.iter().map(|t| (t.name.as_str(), t.slug.as_str())).collect();
+        assert_eq!(tags, vec![("Vue", "vue"), ("Rust", "rust")]);
+        assert_eq!(export.data.users.len(), 1);
+        assert_eq!(export.data.users[0].name, "Jane Doe");
+        assert_eq!(export.data.posts_tags.iter().filter(|r| r.tag_id == export.data.tags[0].id).count(), 3);
+    }
+
+    #[test]
+    fn test_categories() {
+        let yaml = "tags: [rust]\ncategories: [[Dev, Backend], Notes]\nauthors: [Jane, Bob]\n";
+        let frontmatter: crate::models::Frontmatter = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(frontmatter.categories(), vec!["Dev/Backend", "Notes"]);
+        let flat: crate::models::Frontmatter = serde_yaml::from_str("categories: [Diary, Life]\n").unwrap();
+        assert_eq!(flat.categories(), vec!["Diary", "Life"]);
+        let post = || ProcessedMarkdown { frontmatter: frontmatter.clone(), ..Default::default() };
+        let names = |export: &GhostImport| -> Vec<String> {
+            let mut rows: Vec<_> = export.data.posts_tags.iter().collect();
+            rows.sort_by_key(|r| r.sort_order);
+            rows.iter()
+                .map(|r| export.data.tags.iter().find(|t| t.id == r.tag_id).unwrap().name.clone())
+                .collect()
+        };
+
+        let export = GhostExporter::create_export_with_options(vec![post()], &ExportOptions::default()).unwrap();
+        assert_eq!(names(&export), vec!["Dev", "Backend", "Notes", "rust"]);
+        let authors: Vec<_> = export.data.posts_authors.iter().map(|r| r.sort_order).collect();
+        assert_eq!(authors, vec![0, 1]);
+
+        let options = ExportOptions {
+            category_hierarchy: CategoryHierarchy::Join,
+            primary_tag: PrimaryTag::Tag,
+            ..Default::default()
+        };
+        let export = GhostExporter::create_export_with_options(vec![post()], &options).unwrap();
+        assert_eq!(names(&export), vec!["rust", "Dev-Backend", "Notes"]);
+    }
+
+    #[test]
+    fn test_internal_tags() {
+        let yaml = "tags: ['#legacy', imported, Rust]\ninternal_tags: [featured]\n";
+        let frontmatter: crate::models::Frontmatter = serde_yaml::from_str(yaml).unwrap();
+        let post = ProcessedMarkdown { frontmatter, ..Default::default() };
+        let options = ExportOptions {
+            internal_tags: vec!["#imported".to_string()],
+            ..Default::default()
+        };
+
+        let export = GhostExporter::create_export_with_options(vec![post], &options).unwrap();
+        let tags: Vec<_> = export
+            .data
+            .tags
+            .iter()
+            .map(|t| (t.name.as_str(), t.slug.as_str(), t.visibility.as_str()))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("#legacy", "hash-legacy", "internal"),
+                ("imported", "imported", "public"),
+                ("Rust", "rust", "public"),
+                ("#featured", "hash-featured", "internal"),
+                ("#imported", "hash-imported", "internal"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pages() {
+        let file = |path: &str, yaml: &str| ProcessedMarkdown {
+            frontmatter: serde_yaml::from_str(yaml).unwrap(),
+            file_path: path.to_string(),
+            ..Default::default()
+        };
+        let posts = vec![
+            file("hello.md", "title: Hello\ncategory: News"),
+            file("about.md", "title: About\nlayout: page\ncategory: News\ntags: [me]"),
+            file("pages/uses.md", "title: Uses"),
+        ];
+
+        let export = GhostExporter::create_export_with_options(posts, &ExportOptions::default()).unwrap();
+        let types: Vec<_> = export.data.posts.iter().map(|p| p.r#type.as_str()).collect();
+        assert_eq!(types, vec!["post", "page", "page"]);
+        let about: Vec<_> = export.data.posts_tags.iter().filter(|r| r.post_id == export.data.posts[1].id).collect();
+        assert_eq!(about.len(), 1);
+        assert_eq!(export.data.tags.iter().find(|t| t.id == about[0].tag_id).unwrap().name, "me");
+    }
+
+    #[test]
+    fn test_post_status() {
+        let file = |path: &str, yaml: &str| ProcessedMarkdown {
+            frontmatter: serde_yaml::from_str(yaml).unwrap(),
+            file_path: path.to_string(),
+            ..Default::default()
+        };
+        let posts = || {
+            vec![
+                file("old.md", "date: 2020-01-01"),
+                file("future.md", "date: 2999-01-01"),
+                file("hidden.md", "published: false"),
+                file("source/_drafts/idea.md", "title: Idea"),
+                file("news.md", "status: Sent"),
+            ]
+        };
+        let statuses = |export: GhostImport| -> Vec<String> {
+            export.data.posts.into_iter().map(|p| p.status).collect()
+        };
+
+        let export = GhostExporter::create_export_with_options(posts(), &ExportOptions::default()).unwrap();
+        assert_eq!(statuses(export), vec!["published", "scheduled", "draft", "draft", "sent"]);
+
+        let options = ExportOptions { publish_future: true, ..Default::default() };
+        let export = GhostExporter::create_export_with_options(posts(), &options).unwrap();
+        assert_eq!(statuses(export)[1], "published");
+
+        let error = GhostExporter::create_export(vec![file("x.md", "status: live")], None, vec![]).unwrap_err();
+        assert!(error.to_string().starts_with("x.md: unknown status \"live\""));
+
+        for yaml in ["status: scheduled\ndate: 2020-01-01", "status: scheduled"] {
+            let error = GhostExporter::create_export(vec![file("x.md", yaml)], None, vec![]).unwrap_err();
+            assert_eq!(error.to_string(), "x.md: status \"scheduled\" needs a date in the future");
+        }
+        let export = GhostExporter::create_export(vec![file("x.md", "status: scheduled\ndate: 2999-01-01")], None, vec![]);
+        assert_eq!(export.unwrap().data.posts[0].status, "scheduled");
+    }
+
+    #[test]
+    fn test_post_visibility() {
+        let file = |path: &str, yaml: &str| ProcessedMarkdown {
+            frontmatter: serde_yaml::from_str(yaml).unwrap(),
+            file_path: path.to_string(),
+            ..Default::default()
+        };
+        let posts = vec![
+            file("free.md", "title: Free"),
+            file("members.md", "visibility: Members"),
+            file("gold.md", "tiers: [Gold, Silver]"),
+            file("silver.md", "visibility: tiers\ntiers: silver"),
+        ];
+        let options = ExportOptions { default_visibility: PostVisibility::Paid, ..Default::default() };
+        let export = GhostExporter::create_export_with_options(posts, &options).unwrap();
+
+        let visibility: Vec<&str> = export.data.posts.iter().map(|p| p.visibility.as_str()).collect();
+        assert_eq!(visibility, vec!["paid", "members", "tiers", "tiers"]);
+        let tiers: Vec<(&str, &str)> = export.data.products.iter().map(|p| (p.name.as_str(), p.slug.as_str())).collect();
+        assert_eq!(tiers, vec![("Gold", "gold"), ("Silver", "silver")]);
+        let rows: Vec<(i32, i32, i32)> = export
+            .data
+            .posts_products
+            .iter()
+            .map(|r| (r.post_id, r.product_id, r.sort_order))
+            .collect();
+        let (gold, silver) = (export.data.posts[2].id, export.data.posts[3].id);
+        let (gold_tier, silver_tier) = (export.data.products[0].id, export.data.products[1].id);
+        assert_eq!(rows, vec![(gold, gold_tier, 0), (gold, silver_tier, 1), (silver, silver_tier, 0)]);
+
+        let error = |yaml: &str, version: GhostVersion| {
+            let options = ExportOptions { ghost_version: Some(version), ..Default::default() };
+            GhostExporter::create_export_with_options(vec![file("x.md", yaml)], &options)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error("visibility: vip", GhostVersion::V5).starts_with("x.md: unknown visibility \"vip\""));
+        assert!(error("visibility: tiers", GhostVersion::V5).contains("needs a tiers list"));
+        assert!(error("visibility: paid\ntiers: [Gold]", GhostVersion::V5).contains("not \"paid\""));
+        assert!(error("tiers: [Gold]", GhostVersion::V4).contains("cannot import posts restricted to tiers"));
+        assert!(error("visibility: members", GhostVersion::V2).contains("cannot import members-only"));
+    }
+
+    #[test]
+    fn test_seo_fields() {
+        let yaml = r#"
+title: Hello
+description: An excerpt
+image: https://cdn.example.org/cover.png
+canonicalURL: https://old.example.org/hello/
+seo:
+  title: Hello, SEO
+  image: img/social.png
+twitter_description: For Twitter
+cover:
+  alt: A cover
+  caption: Photo by Jane
+"#;
+        let post = ProcessedMarkdown {
+            frontmatter: serde_yaml::from_str(yaml).unwrap(),
+            asset_urls: HashMap::from([(
+                "img/social.png".to_string(),
+                "__GHOST_URL__/content/images/img/social.png".to_string(),
+            )]),
+            ..Default::default()
+        };
+        let export = |version: GhostVersion| {
+            let options = ExportOptions { ghost_version: Some(version), ..Default::default() };
+            GhostExporter::create_export_with_options(vec![post.clone()], &options).unwrap()
+        };
+
+        let import = export(GhostVersion::V5);
+        assert_eq!(import.data.posts[0].canonical_url.as_deref(), Some("https://old.example.org/hello/"));
+        assert_eq!(import.data.posts[0].meta_title, None);
+        let meta = &import.data.posts_meta[0];
+        assert_eq!(meta.post_id, import.data.posts[0].id);
+        assert_eq!(meta.meta_title.as_deref(), Some("Hello, SEO"));
+        assert_eq!(meta.meta_description, None);
+        assert_eq!(meta.og_title.as_deref(), Some("Hello, SEO"));
+        assert_eq!(meta.og_description.as_deref(), Some("An excerpt"));
+        assert_eq!(meta.og_image.as_deref(), Some("__GHOST_URL__/content/images/img/social.png"));
+        assert_eq!(meta.twitter_image, meta.og_image);
+        assert_eq!(meta.twitter_description.as_deref(), Some("For Twitter"));
+        assert_eq!(meta.feature_image_alt.as_deref(), Some("A cover"));
+        assert_eq!(meta.feature_image_caption.as_deref(), Some("Photo by Jane"));
+
+        assert_eq!(export(GhostVersion::V3).data.posts_meta[0].feature_image_alt, None);
+
+        let import = export(GhostVersion::V2);
+        assert!(import.data.posts_meta.is_empty());
+        assert_eq!(import.data.posts[0].meta_title.as_deref(), Some("Hello, SEO"));
+    }
+
+    #[test]
+    fn test_post_excerpts() {
+        let file = |yaml: &str, content: &str| ProcessedMarkdown {
+            frontmatter: serde_yaml::from_str(yaml).unwrap(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let summary = format!("summary: {}", "这是一段很长的摘要，".repeat(40));
+        let posts = vec![
+            file("description: Given", "Body."),
+            file("title: Derived", "# Heading\n\nFirst *paragraph*.\n\nSecond.\n"),
+            file(&summary, "Body."),
+        ];
+        let export = GhostExporter::create_export_with_options(posts, &ExportOptions::default()).unwrap();
+        let excerpts: Vec<_> = export.data.posts.iter().map(|p| p.custom_excerpt.clone().unwrap()).collect();
+
+        assert_eq!(excerpts[0], "Given");
+        assert_eq!(excerpts[1], "First paragraph.");
+        assert_eq!(excerpts[2].chars().count(), MAX_EXCERPT_LENGTH);
+        assert!(excerpts[2].ends_with("摘要…"));
     }
 
     #[test]
@@ -0,0 +1,292 @@
+use crate::models::ghost::GhostImport;
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Columns Ghost exports as booleans but the import models keep as 0/1.
+const FLAG_COLUMNS: [&str; 3] = ["featured", "show_title_and_feature_image", "email_only"];
+
+/// Reads Ghost export files back into a [`GhostImport`].
+///
+/// Exports written by Ghost differ from the files gmi generates: they are
+/// wrapped in `{"db": [...]}`, use string ObjectIds, store flags as booleans
+/// and carry many more columns. Ids are renumbered per table, with every
+/// relation pointing at the new ids, and rows pointing at records that do
+/// not exist are dropped.
+pub struct GhostReader;
+
+impl GhostReader {
+    pub fn read_file(path: &Path) -> Result<GhostImport> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Ghost export {:?}", path))?;
+        Self::parse(&json).with_context(|| format!("Failed to parse Ghost export {:?}", path))
+    }
+
+    pub fn parse(json: &str) -> Result<GhostImport> {
+        let mut value: Value = serde_json::from_str(json)?;
+        if let Some(db) = value.get_mut("db") {
+            value = db
+                .get_mut(0)
+                .map(Value::take)
+                .context("Export has an empty \"db\" list")?;
+        }
+        let Value::Object(mut root) = value else {
+            anyhow::bail!("Export is not a JSON object");
+        };
+
+        let mut data = match root.remove("data") {
+            Some(Value::Object(data)) => data,
+            _ => anyhow::bail!("Export has no \"data\" object"),
+        };
+        normalize_data(&mut data);
+
+        let meta = root.remove("meta").unwrap_or(Value::Null);
+        let meta = json!({
+            "exported_on": meta["exported_on"].as_i64().unwrap_or_default(),
+            "version": meta["version"].as_str().unwrap_or_default(),
+        });
+
+        Ok(serde_json::from_value(
+            json!({ "meta": meta, "data": data }),
+        )?)
+    }
+}
+
+fn normalize_data(data: &mut Map<String, Value>) {
+    let posts = renumber(data, "posts");
+    let tags = renumber(data, "tags");
+    let users = renumber(data, "users");
+    let roles = renumber(data, "roles");
+    let products = renumber(data, "products");
+
+    for post in rows(data, "posts") {
+        normalize_post(post);
+        remap(post, "author_id", &users);
+    }
+    for table in ["tags", "roles", "products"] {
+        for row in rows(data, table) {
+            fill_timestamps(row);
+        }
+    }
+    for user in rows(data, "users") {
+        fill_timestamps(user);
+        if user["email"].is_null() {
+            user["email"] = json!("");
+        }
+    }
+
+    for (table, columns) in [
+        ("posts_tags", vec![("post_id", &posts), ("tag_id", &tags)]),
+        (
+            "posts_authors",
+            vec![("post_id", &posts), ("author_id", &users)],
+        ),
+        ("roles_users", vec![("user_id", &users), ("role_id", &roles)]),
+        (
+            "posts_products",
+            vec![("post_id", &posts), ("product_id", &products)],
+        ),
+        ("posts_meta", vec![("post_id", &posts)]),
+    ] {
+        let Some(Value::Array(list)) = data.get_mut(table) else {
+            data.insert(table.to_string(), json!([]));
+            continue;
+        };
+        list.retain_mut(|row| columns.iter().all(|(column, ids)| remap(row, column, ids)));
+        for (index, row) in list.iter_mut().enumerate() {
+            row["id"] = json!(index + 1);
+        }
+    }
+}
+
+/// Gives every row of `table` a sequential id and returns old id -> new id.
+fn renumber(data: &mut Map<String, Value>, table: &str) -> HashMap<String, i32> {
+    let mut ids = HashMap::new();
+    let list = data.entry(table.to_string()).or_insert_with(|| json!([]));
+    if !list.is_array() {
+        *list = json!([]);
+    }
+    for (index, row) in list.as_array_mut().into_iter().flatten().enumerate() {
+        let new_id = index as i32 + 1;
+        if let Some(old_id) = id_key(&row["id"]) {
+            ids.insert(old_id, new_id);
+        }
+        row["id"] = json!(new_id);
+    }
+    ids
+}
+
+fn rows<'a>(data: &'a mut Map<String, Value>, table: &str) -> impl Iterator<Item = &'a mut Value> {
+    data.get_mut(table)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+/// Points `column` at the renumbered id. Returns false when the referenced
+/// row does not exist; a missing column counts as fine.
+fn remap(row: &mut Value, column: &str, ids: &HashMap<String, i32>) -> bool {
+    match id_key(&row[column]) {
+        Some(old_id) => match ids.get(&old_id) {
+            Some(new_id) => {
+                row[column] = json!(new_id);
+                true
+            }
+            None => {
+                row[column] = Value::Null;
+                false
+            }
+        },
+        None => true,
+    }
+}
+
+fn id_key(id: &Value) -> Option<String> {
+    match id {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+fn normalize_post(post: &mut Value) {
+    for column in FLAG_COLUMNS {
+        if let Some(flag) = post[column].as_bool() {
+            post[column] = json!(flag as i32);
+        }
+    }
+    // Ghost 1.x marked pages with a boolean instead of a type column
+    if post["type"].is_null() {
+        let is_page = post["page"].as_bool().unwrap_or(false) || post["page"] == 1;
+        post["type"] = json!(if is_page { "page" } else { "post" });
+    }
+    if post["visibility"].is_null() {
+        post["visibility"] = json!("public");
+    }
+    if post["status"].is_null() {
+        post["status"] = json!("draft");
+    }
+    if post["title"].is_null() {
+        post["title"] = json!("");
+    }
+    fill_timestamps(post);
+}
+
+fn fill_timestamps(row: &mut Value) {
+    if row["created_at"].is_null() {
+        row["created_at"] = if row["published_at"].is_string() {
+            row["published_at"].clone()
+        } else {
+            json!(chrono::Utc::now())
+        };
+    }
+    if row["updated_at"].is_null() {
+        row["updated_at"] = row["created_at"].clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ghost_export() {
+        let export = json!({
+            "db": [{
+                "meta": { "exported_on": 1700000000000i64, "version": "5.75.1" },
+                "data": {
+                    "posts": [
+                        {
+                            "id": "65a0c0ffee0000000000000a",
+                            "title": "Hello",
+                            "slug": "hello",
+                            "html": "<p>Hi</p>",
+                            "featured": true,
+                            "status": "published",
+                            "type": "post",
+                            "visibility": "public",
+                            "published_at": "2024-01-02T03:04:05.000Z",
+                            "created_at": "2024-01-01T00:00:00.000Z",
+                            "updated_at": "2024-01-03T00:00:00.000Z",
+                            "uuid": "ignored"
+                        },
+                        {
+                            "id": "65a0c0ffee0000000000000b",
+                            "title": "About",
+                            "slug": "about",
+                            "page": true,
+                            "status": "published"
+                        }
+                    ],
+                    "tags": [{
+                        "id": "65a0c0ffee00000000000001",
+                        "name": "Rust",
+                        "slug": "rust",
+                        "created_at": "2024-01-01T00:00:00.000Z"
+                    }],
+                    "users": [{
+                        "id": "1",
+                        "name": "Jane",
+                        "slug": "jane",
+                        "email": "jane@example.org",
+                        "created_at": "2024-01-01T00:00:00.000Z",
+                        "updated_at": "2024-01-01T00:00:00.000Z"
+                    }],
+                    "posts_tags": [
+                        { "id": "x1", "post_id": "65a0c0ffee0000000000000a", "tag_id": "65a0c0ffee00000000000001", "sort_order": 0 },
+                        { "id": "x2", "post_id": "65a0c0ffee0000000000000a", "tag_id": "deleted-tag" }
+                    ],
+                    "posts_authors": [
+                        { "id": "y1", "post_id": "65a0c0ffee0000000000000b", "author_id": "1" }
+                    ],
+                    "products": [{
+                        "id": "65a0c0ffee00000000000f01",
+                        "name": "Gold",
+                        "slug": "gold",
+                        "type": "paid",
+                        "active": true,
+                        "monthly_price_id": null
+                    }],
+                    "posts_products": [
+                        { "id": "z1", "post_id": "65a0c0ffee0000000000000a", "product_id": "65a0c0ffee00000000000f01", "sort_order": 0 }
+                    ],
+                    "posts_meta": [
+                        { "id": "m1", "post_id": "65a0c0ffee0000000000000b", "og_title": "About us", "email_only": false }
+                    ]
+                }
+            }]
+        });
+
+        let import = GhostReader::parse(&export.to_string()).unwrap();
+
+        assert_eq!(import.meta.version, "5.75.1");
+        assert_eq!(import.data.posts.len(), 2);
+        assert_eq!(import.data.posts[0].id, 1);
+        assert_eq!(import.data.posts[0].featured, Some(1));
+        assert_eq!(import.data.posts[1].r#type, "page");
+        assert_eq!(import.data.posts[1].visibility, "public");
+
+        assert_eq!(import.data.posts_tags.len(), 1);
+        assert_eq!(import.data.posts_tags[0].post_id, 1);
+        assert_eq!(import.data.posts_tags[0].tag_id, 1);
+        assert_eq!(import.data.posts_authors[0].post_id, 2);
+        assert_eq!(import.data.posts_authors[0].author_id, 1);
+        assert_eq!(import.data.products[0].slug, "gold");
+        assert_eq!(import.data.posts_products[0].post_id, 1);
+        assert_eq!(import.data.posts_products[0].product_id, 1);
+        assert_eq!(import.data.posts_meta[0].post_id, 2);
+        assert_eq!(import.data.posts_meta[0].og_title.as_deref(), Some("About us"));
+        assert_eq!(
+            import.data.tags[0].updated_at,
+            import.data.tags[0].created_at
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_exports() {
+        assert!(GhostReader::parse("[]").is_err());
+        assert!(GhostReader::parse(r#"{ "db": [] }"#).is_err());
+        assert!(GhostReader::parse(r#"{ "meta": {} }"#).is_err());
+    }
+}
@@ -0,0 +1,653 @@
+use crate::processors::MarkdownProcessor;
+use pulldown_cmark::{html, CodeBlockKind, Event, HeadingLevel, Parser, Tag, TagEnd};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+const FORMAT_BOLD: u64 = 1;
+const FORMAT_ITALIC: u64 = 1 << 1;
+const FORMAT_STRIKETHROUGH: u64 = 1 << 2;
+const FORMAT_CODE: u64 = 1 << 4;
+
+/// Builds Ghost 5 `lexical` documents from Markdown.
+///
+/// Paragraphs, headings, lists, quotes and links become native Lexical nodes;
+/// code blocks, images, rules and raw HTML become the matching Ghost cards,
+/// and a `<!--members-only-->` line becomes the paywall card.
+/// Anything the editor cannot represent natively (tables, footnotes, inline
+/// HTML, lists holding block content) is rendered to an HTML card so nothing
+/// is lost in the conversion.
+pub struct LexicalSerializer<'a> {
+    image_urls: &'a HashMap<String, String>,
+}
+
+impl<'a> LexicalSerializer<'a> {
+    /// `image_urls` maps image references as written in the Markdown to the
+    /// URLs they should carry in the document.
+    pub fn new(image_urls: &'a HashMap<String, String>) -> Self {
+        Self { image_urls }
+    }
+
+    pub fn to_lexical(&self, markdown: &str) -> Value {
+        let parser = Parser::new_ext(markdown, MarkdownProcessor::parser_options());
+        let events: Vec<Event> = parser.collect();
+
+        let mut children = Vec::new();
+        for block in split_blocks(&events) {
+            self.push_block(block, &mut children);
+        }
+
+        json!({
+            "root": {
+                "children": children,
+                "direction": "ltr",
+                "format": "",
+                "indent": 0,
+                "type": "root",
+                "version": 1
+            }
+        })
+    }
+
+    pub fn to_json_string(&self, markdown: &str) -> String {
+        self.to_lexical(markdown).to_string()
+    }
+
+    fn push_block(&self, block: &[Event], out: &mut Vec<Value>) {
+        match &block[0] {
+            Event::Start(Tag::Paragraph) => self.push_paragraph(inner(block), out),
+            Event::Start(Tag::Heading { level, .. }) => {
+                let inline = inner(block);
+                if needs_html(inline) {
+                    out.push(html_card(&self.render_html(block)));
+                } else {
+                    let mut node = element("extended-heading", self.inline_nodes(inline));
+                    node.insert("tag".to_string(), json!(heading_tag(*level)));
+                    out.push(Value::Object(node));
+                }
+            }
+            Event::Start(Tag::BlockQuote) => match self.quote_children(inner(block)) {
+                Some(children) => out.push(Value::Object(element("extended-quote", children))),
+                None => out.push(html_card(&self.render_html(block))),
+            },
+            Event::Start(Tag::List(start)) => match self.list_node(*start, inner(block)) {
+                Some(list) => out.push(list),
+                None => out.push(html_card(&self.render_html(block))),
+            },
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                out.push(json!({
+                    "type": "codeblock",
+                    "version": 1,
+                    "code": plain_text(inner(block)),
+                    "language": language,
+                    "caption": ""
+                }));
+            }
+            Event::Start(Tag::HtmlBlock) => {
+                let raw: String = inner(block)
+                    .iter()
+                    .filter_map(|event| match event {
+                        Event::Html(html) | Event::Text(html) => Some(html.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                out.push(html_block(&raw));
+            }
+            Event::Rule => out.push(json!({ "type": "horizontalrule", "version": 1 })),
+            Event::Html(raw) => out.push(html_block(raw)),
+            // Tables, footnote definitions and anything else without a
+            // native node keep their rendered HTML.
+            _ => out.push(html_card(&self.render_html(block))),
+        }
+    }
+
+    /// Emits a paragraph, lifting standalone images out into image cards.
+    fn push_paragraph(&self, inline: &[Event], out: &mut Vec<Value>) {
+        if needs_html(inline) {
+            out.push(html_card(&self.render_html_paragraph(inline)));
+            return;
+        }
+
+        let mut pending: Vec<Event> = Vec::new();
+        let mut i = 0;
+        while i < inline.len() {
+            let (image, consumed) = match &inline[i] {
+                Event::Start(Tag::Image { .. }) => {
+                    let end = matching_end(inline, i);
+                    (Some(self.image_card(&inline[i..=end], None)), end + 1 - i)
+                }
+                Event::Start(Tag::Link { dest_url, .. })
+                    if matches!(inline.get(i + 1), Some(Event::Start(Tag::Image { .. }))) =>
+                {
+                    let end = matching_end(inline, i);
+                    let image_end = matching_end(inline, i + 1);
+                    if image_end + 1 == end {
+                        let card = self.image_card(&inline[i + 1..=image_end], Some(dest_url));
+                        (Some(card), end + 1 - i)
+                    } else {
+                        (None, 1)
+                    }
+                }
+                _ => (None, 1),
+            };
+
+            match image {
+                Some(card) => {
+                    self.flush_paragraph(&mut pending, out);
+                    out.push(card);
+                }
+                None => pending.push(inline[i].clone()),
+            }
+            i += consumed;
+        }
+        self.flush_paragraph(&mut pending, out);
+    }
+
+    fn flush_paragraph(&self, pending: &mut Vec<Event>, out: &mut Vec<Value>) {
+        let children = self.inline_nodes(pending);
+        pending.clear();
+        let blank = children.iter().all(|child| {
+            child["type"] == "extended-text"
+                && child["text"].as_str().is_some_and(|t| t.trim().is_empty())
+        });
+        if !blank {
+            out.push(Value::Object(element("paragraph", children)));
+        }
+    }
+
+    fn image_card(&self, image: &[Event], href: Option<&str>) -> Value {
+        let Event::Start(Tag::Image { dest_url, title, .. }) = &image[0] else {
+            unreachable!("image_card called without an image");
+        };
+        json!({
+            "type": "image",
+            "version": 1,
+            "src": self.image_url(dest_url),
+            "width": null,
+            "height": null,
+            "title": title.as_ref(),
+            "alt": plain_text(inner(image)),
+            "caption": "",
+            "cardWidth": "regular",
+            "href": href.unwrap_or("")
+        })
+    }
+
+    fn image_url(&self, src: &str) -> String {
+        self.image_urls
+            .get(src)
+            .cloned()
+            .unwrap_or_else(|| src.to_string())
+    }
+
+    /// HTML for blocks without a native node, with images pointing where
+    /// the image cards do.
+    fn render_html(&self, events: &[Event]) -> String {
+        let events = events.iter().cloned().map(|event| match event {
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: self.image_url(&dest_url).into(),
+                title,
+                id,
+            }),
+            other => other,
+        });
+        let mut output = String::new();
+        html::push_html(&mut output, events);
+        output.trim_end().to_string()
+    }
+
+    fn render_html_paragraph(&self, inline: &[Event]) -> String {
+        let mut events = vec![Event::Start(Tag::Paragraph)];
+        events.extend(inline.iter().cloned());
+        events.push(Event::End(TagEnd::Paragraph));
+        self.render_html(&events)
+    }
+
+    /// Quotes only hold inline content in Ghost; paragraphs are joined with
+    /// line breaks and any other block content falls back to HTML.
+    fn quote_children(&self, events: &[Event]) -> Option<Vec<Value>> {
+        let mut children = Vec::new();
+        for block in split_blocks(events) {
+            if !matches!(block[0], Event::Start(Tag::Paragraph)) || needs_html(inner(block)) {
+                return None;
+            }
+            if contains_image(inner(block)) {
+                return None;
+            }
+            if !children.is_empty() {
+                children.push(linebreak());
+            }
+            children.extend(self.inline_nodes(inner(block)));
+        }
+        Some(children)
+    }
+
+    fn list_node(&self, start: Option<u64>, events: &[Event]) -> Option<Value> {
+        let mut items = Vec::new();
+        for item in split_blocks(events) {
+            self.list_items(inner(item), &mut items)?;
+        }
+        for (index, item) in items.iter_mut().enumerate() {
+            item["value"] = json!(start.unwrap_or(1) + index as u64);
+        }
+
+        let mut node = element("list", items);
+        let (list_type, tag) = if start.is_some() { ("number", "ol") } else { ("bullet", "ul") };
+        node.insert("listType".to_string(), json!(list_type));
+        node.insert("start".to_string(), json!(start.unwrap_or(1)));
+        node.insert("tag".to_string(), json!(tag));
+        Some(Value::Object(node))
+    }
+
+    /// Converts one Markdown list item into Lexical list items. Lexical keeps
+    /// nested lists in a list item of their own, so an item holding text and a
+    /// sub-list becomes two siblings.
+    fn list_items(&self, events: &[Event], items: &mut Vec<Value>) -> Option<()> {
+        let mut inline_children: Vec<Value> = Vec::new();
+        let mut loose = false;
+        let mut i = 0;
+        while i < events.len() {
+            match &events[i] {
+                Event::Start(Tag::Paragraph) => {
+                    let end = matching_end(events, i);
+                    let paragraph = &events[i + 1..end];
+                    if needs_html(paragraph) || contains_image(paragraph) {
+                        return None;
+                    }
+                    if loose && !inline_children.is_empty() {
+                        inline_children.push(linebreak());
+                    }
+                    inline_children.extend(self.inline_nodes(paragraph));
+                    loose = true;
+                    i = end + 1;
+                }
+                Event::Start(Tag::List(start)) => {
+                    let end = matching_end(events, i);
+                    let nested = self.list_node(*start, &events[i + 1..end])?;
+                    if !inline_children.is_empty() {
+                        items.push(list_item(std::mem::take(&mut inline_children)));
+                    }
+                    items.push(list_item(vec![nested]));
+                    i = end + 1;
+                }
+                Event::Start(Tag::Image { .. })
+                | Event::Start(Tag::CodeBlock(_))
+                | Event::Start(Tag::BlockQuote)
+                | Event::Start(Tag::Heading { .. })
+                | Event::Start(Tag::HtmlBlock)
+                | Event::Start(Tag::Table(_))
+                | Event::Html(_)
+                | Event::InlineHtml(_)
+                | Event::FootnoteReference(_)
+                | Event::Rule => return None,
+                _ => {
+                    let end = if matches!(events[i], Event::Start(_)) {
+                        matching_end(events, i)
+                    } else {
+                        i
+                    };
+                    let inline = &events[i..=end];
+                    if needs_html(inline) || contains_image(inline) {
+                        return None;
+                    }
+                    inline_children.extend(self.inline_nodes(inline));
+                    i = end + 1;
+                }
+            }
+        }
+        if !inline_children.is_empty() || items.is_empty() {
+            items.push(list_item(merge_text(inline_children)));
+        }
+        Some(())
+    }
+
+    fn inline_nodes(&self, events: &[Event]) -> Vec<Value> {
+        let mut root: Vec<Value> = Vec::new();
+        let mut links: Vec<(Map<String, Value>, Vec<Value>)> = Vec::new();
+        let mut format: u64 = 0;
+        let mut skip_depth = 0usize;
+
+        for event in events {
+            if skip_depth > 0 {
+                match event {
+                    Event::Start(_) => skip_depth += 1,
+                    Event::End(_) => skip_depth -= 1,
+                    _ => {}
+                }
+                continue;
+            }
+
+            let current = match links.last_mut() {
+                Some((_, children)) => children,
+                None => &mut root,
+            };
+
+            match event {
+                Event::Text(text) => current.push(text_node(text, format)),
+                Event::Code(code) => current.push(text_node(code, format | FORMAT_CODE)),
+                Event::SoftBreak => current.push(text_node(" ", format)),
+                Event::HardBreak => current.push(linebreak()),
+                Event::TaskListMarker(checked) => {
+                    current.push(text_node(if *checked { "[x] " } else { "[ ] " }, format))
+                }
+                Event::Start(Tag::Emphasis) => format |= FORMAT_ITALIC,
+                Event::End(TagEnd::Emphasis) => format &= !FORMAT_ITALIC,
+                Event::Start(Tag::Strong) => format |= FORMAT_BOLD,
+                Event::End(TagEnd::Strong) => format &= !FORMAT_BOLD,
+                Event::Start(Tag::Strikethrough) => format |= FORMAT_STRIKETHROUGH,
+                Event::End(TagEnd::Strikethrough) => format &= !FORMAT_STRIKETHROUGH,
+                Event::Start(Tag::Link { dest_url, title, .. }) => {
+                    let mut link = Map::new();
+                    link.insert("url".to_string(), json!(dest_url.as_ref()));
+                    let title = (!title.is_empty()).then(|| title.to_string());
+                    link.insert("title".to_string(), json!(title));
+                    links.push((link, Vec::new()));
+                }
+                // Images nested where a card cannot go become links to the file.
+                Event::Start(Tag::Image { dest_url, .. }) => {
+                    let mut link = Map::new();
+                    link.insert("url".to_string(), json!(self.image_url(dest_url)));
+                    link.insert("title".to_string(), Value::Null);
+                    links.push((link, Vec::new()));
+                }
+                Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                    if let Some((attrs, children)) = links.pop() {
+                        let mut node = element("link", merge_text(children));
+                        node.insert("rel".to_string(), Value::Null);
+                        node.insert("target".to_string(), Value::Null);
+                        node.extend(attrs);
+                        let parent = match links.last_mut() {
+                            Some((_, children)) => children,
+                            None => &mut root,
+                        };
+                        parent.push(Value::Object(node));
+                    }
+                }
+                Event::Start(Tag::Paragraph) | Event::End(TagEnd::Paragraph) => {}
+                Event::Start(_) => skip_depth = 1,
+                _ => {}
+            }
+        }
+
+        merge_text(root)
+    }
+}
+
+/// Splits a flat event list into its top-level blocks.
+fn split_blocks<'e, 'a>(events: &'e [Event<'a>]) -> Vec<&'e [Event<'a>]> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        let end = if matches!(events[i], Event::Start(_)) {
+            matching_end(events, i)
+        } else {
+            i
+        };
+        blocks.push(&events[i..=end]);
+        i = end + 1;
+    }
+    blocks
+}
+
+/// Index of the `End` event closing the `Start` event at `start`.
+fn matching_end(events: &[Event], start: usize) -> usize {
+    let mut depth = 0usize;
+    for (offset, event) in events[start..].iter().enumerate() {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + offset;
+                }
+            }
+            _ => {}
+        }
+    }
+    events.len() - 1
+}
+
+/// The events between a block's `Start` and `End`.
+fn inner<'e, 'a>(block: &'e [Event<'a>]) -> &'e [Event<'a>] {
+    if block.len() >= 2 && matches!(block[0], Event::Start(_)) {
+        &block[1..block.len() - 1]
+    } else {
+        &[]
+    }
+}
+
+/// Inline content Lexical text nodes cannot carry without losing meaning.
+fn needs_html(events: &[Event]) -> bool {
+    events
+        .iter()
+        .any(|event| matches!(event, Event::InlineHtml(_) | Event::FootnoteReference(_)))
+}
+
+fn contains_image(events: &[Event]) -> bool {
+    events
+        .iter()
+        .any(|event| matches!(event, Event::Start(Tag::Image { .. })))
+}
+
+fn plain_text(events: &[Event]) -> String {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+            Event::SoftBreak | Event::HardBreak => Some(" "),
+            _ => None,
+        })
+        .collect()
+}
+
+fn heading_tag(level: HeadingLevel) -> &'static str {
+    match level {
+        HeadingLevel::H1 => "h1",
+        HeadingLevel::H2 => "h2",
+        HeadingLevel::H3 => "h3",
+        HeadingLevel::H4 => "h4",
+        HeadingLevel::H5 => "h5",
+        HeadingLevel::H6 => "h6",
+    }
+}
+
+fn element(node_type: &str, children: Vec<Value>) -> Map<String, Value> {
+    let mut node = Map::new();
+    node.insert("children".to_string(), Value::Array(children));
+    node.insert("direction".to_string(), json!("ltr"));
+    node.insert("format".to_string(), json!(""));
+    node.insert("indent".to_string(), json!(0));
+    node.insert("type".to_string(), json!(node_type));
+    node.insert("version".to_string(), json!(1));
+    node
+}
+
+fn list_item(children: Vec<Value>) -> Value {
+    let mut node = element("listitem", children);
+    node.insert("value".to_string(), json!(1));
+    node.insert("checked".to_string(), Value::Null);
+    Value::Object(node)
+}
+
+fn text_node(text: &str, format: u64) -> Value {
+    json!({
+        "detail": 0,
+        "format": format,
+        "mode": "normal",
+        "style": "",
+        "text": text,
+        "type": "extended-text",
+        "version": 1
+    })
+}
+
+fn linebreak() -> Value {
+    json!({ "type": "linebreak", "version": 1 })
+}
+
+/// An HTML card, or the paywall card for the members-only marker.
+fn html_block(raw: &str) -> Value {
+    if MarkdownProcessor::is_paywall_marker(raw) {
+        json!({ "type": "paywall", "version": 1 })
+    } else {
+        html_card(raw.trim_end())
+    }
+}
+
+pub(crate) fn html_card(html: &str) -> Value {
+    json!({ "type": "html", "version": 1, "html": html })
+}
+
+/// Joins adjacent text nodes that share a format, which pulldown-cmark splits
+/// on every escape and soft break.
+fn merge_text(nodes: Vec<Value>) -> Vec<Value> {
+    let mut merged: Vec<Value> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        if node["type"] == "extended-text" {
+            if let Some(last) = merged.last_mut() {
+                if last["type"] == "extended-text" && last["format"] == node["format"] {
+                    let text = format!(
+                        "{}{}",
+                        last["text"].as_str().unwrap_or(""),
+                        node["text"].as_str().unwrap_or("")
+                    );
+                    last["text"] = json!(text);
+                    continue;
+                }
+            }
+        }
+        merged.push(node);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(markdown: &str) -> Vec<Value> {
+        let urls = HashMap::new();
+        let doc = LexicalSerializer::new(&urls).to_lexical(markdown);
+        doc["root"]["children"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn test_paragraphs_and_headings() {
+        let nodes = convert("# Title\n\nSome **bold** and `code` with a [link](https://x.org).");
+
+        assert_eq!(nodes[0]["type"], "extended-heading");
+        assert_eq!(nodes[0]["tag"], "h1");
+        assert_eq!(nodes[0]["children"][0]["text"], "Title");
+
+        let paragraph = nodes[1]["children"].as_array().unwrap();
+        assert_eq!(nodes[1]["type"], "paragraph");
+        assert_eq!(paragraph[0]["text"], "Some ");
+        assert_eq!(paragraph[1]["text"], "bold");
+        assert_eq!(paragraph[1]["format"], FORMAT_BOLD);
+        assert_eq!(paragraph[3]["format"], FORMAT_CODE);
+        assert_eq!(paragraph[5]["type"], "link");
+        assert_eq!(paragraph[5]["url"], "https://x.org");
+        assert_eq!(paragraph[5]["children"][0]["text"], "link");
+    }
+
+    #[test]
+    fn test_code_blocks_keep_content() {
+        let nodes = convert("```rust\nfn main() {\n    println!(\"<hi>\");\n}\n```\n");
+
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0]["type"], "codeblock");
+        assert_eq!(nodes[0]["language"], "rust");
+        assert_eq!(nodes[0]["code"], "fn main() {\n    println!(\"<hi>\");\n}\n");
+    }
+
+    #[test]
+    fn test_lists() {
+        let nodes = convert("- one\n- two\n  - nested\n\n3. three\n4. four\n");
+
+        assert_eq!(nodes[0]["type"], "list");
+        assert_eq!(nodes[0]["listType"], "bullet");
+        let items = nodes[0]["children"].as_array().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[1]["children"][0]["text"], "two");
+        assert_eq!(items[2]["children"][0]["type"], "list");
+
+        assert_eq!(nodes[1]["listType"], "number");
+        assert_eq!(nodes[1]["start"], 3);
+        assert_eq!(nodes[1]["children"][1]["value"], 4);
+    }
+
+    #[test]
+    fn test_images_become_cards() {
+        let mut urls = HashMap::new();
+        urls.insert(
+            "./img/a.png".to_string(),
+            "__GHOST_URL__/content/images/img/a.png".to_string(),
+        );
+        let doc = LexicalSerializer::new(&urls).to_lexical("Before ![Alt](./img/a.png) after");
+        let nodes = doc["root"]["children"].as_array().unwrap();
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0]["children"][0]["text"], "Before ");
+        assert_eq!(nodes[1]["type"], "image");
+        assert_eq!(nodes[1]["src"], "__GHOST_URL__/content/images/img/a.png");
+        assert_eq!(nodes[1]["alt"], "Alt");
+        assert_eq!(nodes[2]["children"][0]["text"], " after");
+    }
+
+    #[test]
+    fn test_images_inside_html_cards() {
+        let mut urls = HashMap::new();
+        urls.insert(
+            "img/a.png".to_string(),
+            "__GHOST_URL__/content/images/img/a.png".to_string(),
+        );
+        let markdown = "- item ![A](img/a.png)\n  ```\n  code\n  ```\n\n| a |\n|---|\n| ![A](img/a.png) |\n";
+        let doc = LexicalSerializer::new(&urls).to_lexical(markdown);
+        let nodes = doc["root"]["children"].as_array().unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        for node in nodes {
+            assert_eq!(node["type"], "html");
+            let html = node["html"].as_str().unwrap();
+            assert!(html.contains("src=\"__GHOST_URL__/content/images/img/a.png\""), "{}", html);
+        }
+    }
+
+    #[test]
+    fn test_html_cards_for_unsupported_blocks() {
+        let nodes = convert("| a | b |\n|---|---|\n| 1 | 2 |\n\nText[^1]\n\n[^1]: Note\n\n<div class=\"x\">raw</div>\n\n---\n");
+
+        assert_eq!(nodes[0]["type"], "html");
+        assert!(nodes[0]["html"].as_str().unwrap().starts_with("<table>"));
+        assert_eq!(nodes[1]["type"], "html");
+        assert!(nodes[1]["html"].as_str().unwrap().contains("footnote-reference"));
+        assert_eq!(nodes[2]["type"], "html");
+        assert!(nodes[2]["html"].as_str().unwrap().contains("footnote-definition"));
+        assert_eq!(nodes[3]["html"], "<div class=\"x\">raw</div>");
+        assert_eq!(nodes[4]["type"], "horizontalrule");
+    }
+
+    #[test]
+    fn test_paywall_marker() {
+        let nodes = convert("Free preview\n\n<!-- members-only -->\n\nFor members\n\n```html\n<!--members-only-->\n```\n");
+
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[1], json!({ "type": "paywall", "version": 1 }));
+        assert_eq!(nodes[3]["type"], "codeblock");
+    }
+
+    #[test]
+    fn test_quotes() {
+        let nodes = convert("> first\n>\n> second\n\n> - list in quote\n");
+
+        assert_eq!(nodes[0]["type"], "extended-quote");
+        assert_eq!(nodes[0]["children"][1]["type"], "linebreak");
+        assert_eq!(nodes[1]["type"], "html");
+    }
+}
@@ -1,28 +1,41 @@
 use crate::models::{Frontmatter, ProcessedMarkdown};
+use crate::processors::SlugStyle;
 use anyhow::{Context, Result};
 use chrono::{DateTime, TimeZone, Utc};
-use pulldown_cmark::{html, Options, Parser};
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
 use regex::Regex;
+use std::collections::HashMap;
 use std::fs;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 pub struct MarkdownProcessor;
 
+/// Frontmatter flavours recognised at the top of a Markdown file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontmatterFormat {
+    /// `---` delimited YAML (Jekyll, Hexo, Hugo, Next.js starters)
+    Yaml,
+    /// `+++` delimited TOML (Hugo, Zola)
+    Toml,
+    /// A leading JSON object (Hugo)
+    Json,
+}
+
 impl MarkdownProcessor {
+    /// Comment that ends the public preview of a members-only post, where
+    /// Ghost puts its paywall card.
+    pub const PAYWALL_MARKER: &'static str = "<!--members-only-->";
+
     pub fn process_file(file_path: &Path) -> Result<ProcessedMarkdown> {
         let content = fs::read_to_string(file_path)
             .with_context(|| format!("Failed to read file: {:?}", file_path))?;
 
-        let (frontmatter, markdown_content) = Self::extract_frontmatter(&content)?;
+        let (format, frontmatter, markdown_content) = Self::extract_frontmatter(&content)?;
         let html_content = Self::markdown_to_html(&markdown_content);
         let images = Self::extract_images(&html_content);
 
-        let parsed_frontmatter = Self::parse_frontmatter(&frontmatter)?;
-        
-        println!("DEBUG: File: {:?}", file_path);
-        println!("DEBUG: Frontmatter extracted: {} chars", frontmatter.len());
-        println!("DEBUG: Markdown content: {} chars", markdown_content.len());
-        println!("DEBUG: Parsed title: {:?}", parsed_frontmatter.title);
+        let parsed_frontmatter = Self::parse_frontmatter(format, &frontmatter)?;
 
         Ok(ProcessedMarkdown {
             frontmatter: parsed_frontmatter,
@@ -30,60 +43,198 @@ impl MarkdownProcessor {
             html_content,
             file_path: file_path.to_string_lossy().to_string(),
             images,
+            ..Default::default()
         })
     }
 
-    fn extract_frontmatter(content: &str) -> Result<(String, String)> {
+    fn extract_frontmatter(content: &str) -> Result<(FrontmatterFormat, String, String)> {
         let re = Regex::new(r"(?s)^[\s\n]*---\r?\n(.*?)\r?\n---\r?\n(.*)$").unwrap();
         let re_alt = Regex::new(r"(?s)^[\s\n]*---\r?\n(.*?)\r?\n---(.*)$").unwrap();
+        let re_toml = Regex::new(r"(?s)^\+\+\+\r?\n(.*?)\r?\n\+\+\+[ \t]*(?:\r?\n(.*))?$").unwrap();
         
         let content = content.trim_start();
         
         if let Some(captures) = re.captures(content) {
             let frontmatter = captures.get(1).unwrap().as_str().to_string();
             let markdown = captures.get(2).unwrap().as_str().to_string();
-            Ok((frontmatter, markdown))
+            Ok((FrontmatterFormat::Yaml, frontmatter, markdown))
         } else if let Some(captures) = re_alt.captures(content) {
             let frontmatter = captures.get(1).unwrap().as_str().to_string();
             let markdown = captures.get(2).unwrap().as_str().to_string();
-            Ok((frontmatter, markdown))
+            Ok((FrontmatterFormat::Yaml, frontmatter, markdown))
+        } else if let Some(captures) = re_toml.captures(content) {
+            let frontmatter = captures.get(1).unwrap().as_str().to_string();
+            let markdown = captures.get(2).map_or("", |m| m.as_str()).to_string();
+            Ok((FrontmatterFormat::Toml, frontmatter, markdown))
+        } else if let Some(end) = Self::json_frontmatter_end(content) {
+            let frontmatter = content[..end].to_string();
+            let markdown = content[end..].trim_start_matches([' ', '\t', '\r', '\n']).to_string();
+            Ok((FrontmatterFormat::Json, frontmatter, markdown))
         } else {
-            Ok((String::new(), content.to_string()))
+            Ok((FrontmatterFormat::Yaml, String::new(), content.to_string()))
         }
     }
 
-    fn parse_frontmatter(frontmatter_str: &str) -> Result<Frontmatter> {
+    /// Byte offset just past a leading JSON object, if the content starts with one.
+    fn json_frontmatter_end(content: &str) -> Option<usize> {
+        if !content.starts_with('{') {
+            return None;
+        }
+        let mut stream = serde_json::Deserializer::from_str(content)
+            .into_iter::<serde_json::Value>();
+        match stream.next() {
+            Some(Ok(serde_json::Value::Object(_))) => Some(stream.byte_offset()),
+            _ => None,
+        }
+    }
+
+    fn parse_frontmatter(format: FrontmatterFormat, frontmatter_str: &str) -> Result<Frontmatter> {
         if frontmatter_str.trim().is_empty() {
             return Ok(Frontmatter::default());
         }
 
-        println!("DEBUG: Parsing frontmatter:\n{}\n", frontmatter_str);
-        
-        let frontmatter: Frontmatter = serde_yaml::from_str(frontmatter_str)
-            .map_err(|e| {
-                println!("DEBUG: YAML parsing error: {}", e);
-                e
-            })
-            .context("Failed to parse YAML frontmatter")?;
-        
-        println!("DEBUG: Parsed frontmatter: {:?}", frontmatter);
-        Ok(frontmatter)
+        let value = match format {
+            FrontmatterFormat::Yaml => serde_yaml::from_str(frontmatter_str)
+                .context("Failed to parse YAML frontmatter")?,
+            FrontmatterFormat::Toml => {
+                let table: toml::Value = toml::from_str(frontmatter_str)
+                    .context("Failed to parse TOML frontmatter")?;
+                Self::toml_to_yaml(table)
+            }
+            FrontmatterFormat::Json => {
+                let json: serde_json::Value = serde_json::from_str(frontmatter_str)
+                    .context("Failed to parse JSON frontmatter")?;
+                serde_yaml::to_value(json)?
+            }
+        };
+
+        serde_yaml::from_value(Self::normalize_frontmatter(value))
+            .context("Failed to read frontmatter fields")
     }
 
-    fn markdown_to_html(markdown: &str) -> String {
+    /// Hoists Zola's `[taxonomies]` and `[extra]` tables to the top level so
+    /// `tags`, `categories`, `image` and friends land in the usual fields.
+    /// Keys already present at the top level win.
+    fn normalize_frontmatter(value: serde_yaml::Value) -> serde_yaml::Value {
+        let serde_yaml::Value::Mapping(mut map) = value else {
+            return value;
+        };
+
+        for table in ["taxonomies", "extra"] {
+            if let Some(serde_yaml::Value::Mapping(nested)) = map.remove(table) {
+                for (key, value) in nested {
+                    if !map.contains_key(&key) {
+                        map.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        serde_yaml::Value::Mapping(map)
+    }
+
+    fn toml_to_yaml(value: toml::Value) -> serde_yaml::Value {
+        match value {
+            toml::Value::String(s) => serde_yaml::Value::String(s),
+            toml::Value::Integer(i) => serde_yaml::Value::Number(i.into()),
+            toml::Value::Float(f) => serde_yaml::Value::Number(f.into()),
+            toml::Value::Boolean(b) => serde_yaml::Value::Bool(b),
+            toml::Value::Datetime(dt) => serde_yaml::Value::String(dt.to_string()),
+            toml::Value::Array(items) => {
+                serde_yaml::Value::Sequence(items.into_iter().map(Self::toml_to_yaml).collect())
+            }
+            toml::Value::Table(table) => serde_yaml::Value::Mapping(
+                table
+                    .into_iter()
+                    .map(|(k, v)| (serde_yaml::Value::String(k), Self::toml_to_yaml(v)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Markdown extensions enabled for every conversion, so the HTML and
+    /// Lexical outputs agree on what the source means.
+    pub fn parser_options() -> Options {
         let mut options = Options::empty();
         options.insert(Options::ENABLE_TABLES);
         options.insert(Options::ENABLE_FOOTNOTES);
         options.insert(Options::ENABLE_STRIKETHROUGH);
         options.insert(Options::ENABLE_TASKLISTS);
+        options
+    }
 
-        let parser = Parser::new_ext(markdown, options);
+    fn markdown_to_html(markdown: &str) -> String {
+        let parser = Parser::new_ext(markdown, Self::parser_options());
         let mut html_output = String::new();
         html::push_html(&mut html_output, parser);
         
         html_output
     }
 
+    /// Replaces image references in Markdown source with the URLs in
+    /// `image_urls`, covering both `![alt](src)` images and raw `<img>` tags.
+    pub fn rewrite_image_urls(markdown: &str, image_urls: &HashMap<String, String>) -> String {
+        if image_urls.is_empty() {
+            return markdown.to_string();
+        }
+
+        let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+        for (event, range) in Parser::new_ext(markdown, Self::parser_options()).into_offset_iter() {
+            match event {
+                Event::Start(Tag::Image { dest_url, .. }) => {
+                    let Some(url) = image_urls.get(dest_url.as_ref()) else {
+                        continue;
+                    };
+                    if let Some(offset) = markdown[range.clone()].find(dest_url.as_ref()) {
+                        let start = range.start + offset;
+                        replacements.push((start..start + dest_url.len(), url.clone()));
+                    }
+                }
+                Event::Html(raw) | Event::InlineHtml(raw) => {
+                    let mut html = raw.to_string();
+                    for (src, url) in image_urls {
+                        html = html
+                            .replace(&format!("src=\"{}\"", src), &format!("src=\"{}\"", url))
+                            .replace(&format!("src='{}'", src), &format!("src='{}'", url));
+                    }
+                    if html != raw.as_ref() {
+                        if let Some(offset) = markdown[range.clone()].find(raw.as_ref()) {
+                            let start = range.start + offset;
+                            replacements.push((start..start + raw.len(), html));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut output = markdown.to_string();
+        for (range, url) in replacements.into_iter().rev() {
+            output.replace_range(range, &url);
+        }
+        output
+    }
+
+    /// Whether a raw HTML block is the paywall marker, spaced or not.
+    pub fn is_paywall_marker(html: &str) -> bool {
+        html.trim()
+            .strip_prefix("<!--")
+            .and_then(|comment| comment.strip_suffix("-->"))
+            .is_some_and(|comment| comment.trim() == "members-only")
+    }
+
+    /// Source ranges of the paywall markers in `markdown`, skipping ones
+    /// inside code blocks.
+    pub fn paywall_markers(markdown: &str) -> Vec<Range<usize>> {
+        Parser::new_ext(markdown, Self::parser_options())
+            .into_offset_iter()
+            .filter_map(|(event, range)| match event {
+                Event::Html(raw) if Self::is_paywall_marker(&raw) => Some(range),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn extract_images(html: &str) -> Vec<String> {
         let re = Regex::new(r#"<img[^>]+src="([^"]+)""#).unwrap();
         re.captures_iter(html)
@@ -92,12 +243,7 @@ impl MarkdownProcessor {
     }
 
     pub fn generate_slug(title: &str) -> String {
-        let re = Regex::new(r"[^\w\s-]").unwrap();
-        let slug = re.replace_all(title, "");
-        let slug = slug.to_lowercase();
-        let slug = slug.replace(" ", "-");
-        let slug = slug.replace("--", "-");
-        slug.trim_matches('-').to_string()
+        SlugStyle::default().slugify(title)
     }
 
     pub fn parse_date(date_str: &str) -> Result<DateTime<Utc>> {
@@ -135,14 +281,14 @@ impl MarkdownProcessor {
                 .filter_map(|e| e.ok())
             {
                 let path = entry.path();
-                if path.is_file() && path.extension().map_or(false, |ext| ext == "md") {
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
                     files.push(path.to_path_buf());
                 }
             }
         } else {
             for entry in std::fs::read_dir(root_path)? {
                 let path = entry?.path();
-                if path.is_file() && path.extension().map_or(false, |ext| ext == "md") {
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
                     files.push(path);
                 }
             }
@@ -156,18 +302,50 @@ impl MarkdownProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
 
     #[test]
     fn test_extract_frontmatter() {
         let content = "---\ntitle: Test Post\n---\n# Hello World\nThis is content.";
-        let (frontmatter, markdown) = MarkdownProcessor::extract_frontmatter(content).unwrap();
+        let (format, frontmatter, markdown) = MarkdownProcessor::extract_frontmatter(content).unwrap();
         
+        assert_eq!(format, FrontmatterFormat::Yaml);
         assert!(frontmatter.contains("title: Test Post"));
         assert_eq!(markdown.trim(), "# Hello World\nThis is content.");
     }
 
+    #[test]
+    fn test_toml_frontmatter() {
+        let content = "+++\ntitle = \"Zola Post\"\ndate = 2021-03-04T10:00:00Z\ndraft = true\n\n[taxonomies]\ntags = [\"rust\", \"zola\"]\ncategories = [\"dev\"]\n\n[extra]\nimage = \"cover.png\"\n+++\n# Body\n";
+        let (format, frontmatter, markdown) = MarkdownProcessor::extract_frontmatter(content).unwrap();
+        assert_eq!(format, FrontmatterFormat::Toml);
+        assert_eq!(markdown, "# Body\n");
+
+        let parsed = MarkdownProcessor::parse_frontmatter(format, &frontmatter).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Zola Post"));
+        assert_eq!(parsed.date.as_deref(), Some("2021-03-04T10:00:00Z"));
+        assert_eq!(parsed.draft, Some(true));
+        assert_eq!(parsed.tags, Some(vec!["rust".to_string(), "zola".to_string()]));
+        assert_eq!(parsed.image.as_deref(), Some("cover.png"));
+        assert!(parsed.extra.contains_key("categories"));
+        assert!(!parsed.extra.contains_key("taxonomies"));
+    }
+
+    #[test]
+    fn test_json_frontmatter() {
+        let content = "{\n  \"title\": \"JSON Post\",\n  \"tags\": [\"a\", \"b\"],\n  \"featured\": true\n}\n\nBody with {braces}.\n";
+        let (format, frontmatter, markdown) = MarkdownProcessor::extract_frontmatter(content).unwrap();
+        assert_eq!(format, FrontmatterFormat::Json);
+        assert_eq!(markdown, "Body with {braces}.\n");
+
+        let parsed = MarkdownProcessor::parse_frontmatter(format, &frontmatter).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("JSON Post"));
+        assert_eq!(parsed.tags.unwrap().len(), 2);
+        assert_eq!(parsed.featured, Some(true));
+
+        let (_, frontmatter, _) = MarkdownProcessor::extract_frontmatter("{not json} text").unwrap();
+        assert!(frontmatter.is_empty());
+    }
+
     #[test]
     fn test_markdown_to_html() {
         let markdown = "# Hello\n\nThis is **bold** text.";
@@ -177,6 +355,18 @@ mod tests {
         assert!(html.contains("<strong>bold</strong>"));
     }
 
+    #[test]
+    fn test_rewrite_image_urls() {
+        let mut urls = HashMap::new();
+        urls.insert("./a.png".to_string(), "__GHOST_URL__/content/images/a.png".to_string());
+        let markdown = "![one](./a.png \"Title\") and ![two](https://x.org/b.png)\n\n<img src=\"./a.png\">\n";
+
+        assert_eq!(
+            MarkdownProcessor::rewrite_image_urls(markdown, &urls),
+            "![one](__GHOST_URL__/content/images/a.png \"Title\") and ![two](https://x.org/b.png)\n\n<img src=\"__GHOST_URL__/content/images/a.png\">\n"
+        );
+    }
+
     #[test]
     fn test_generate_slug() {
         assert_eq!(MarkdownProcessor::generate_slug("Hello World"), "hello-world");
@@ -0,0 +1,523 @@
+use crate::models::ghost::{GhostImport, Post, PostVisibility};
+use crate::processors::{percent_decode, MarkdownProcessor, MarkdownWriter, SlugStyle};
+use anyhow::{Context, Result};
+use chrono::SecondsFormat;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// Directory, relative to the output root, that receives post images.
+const IMAGES_DIR: &str = "images";
+
+/// Directory, relative to the output root, that receives pages.
+const PAGES_DIR: &str = "pages";
+
+/// Where images hosted by the Ghost site can be found.
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownExportOptions {
+    /// Ghost's `content` directory (or its `images` folder) to copy from
+    pub content_dir: Option<PathBuf>,
+    /// Site URL, used for `__GHOST_URL__` links and to download images that
+    /// are not found locally
+    pub site_url: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct MarkdownExportReport {
+    pub files: Vec<PathBuf>,
+    pub images: Vec<PathBuf>,
+    /// Slug of the post and the image that could not be fetched
+    pub missing_images: Vec<(String, String)>,
+}
+
+/// Writes the posts of a Ghost export as Markdown files with YAML
+/// frontmatter, using the keys `Frontmatter` reads so the files can be
+/// imported again. Pages go into `pages/`, and images hosted by the site are
+/// copied or downloaded into `images/`.
+pub struct MarkdownExporter;
+
+impl MarkdownExporter {
+    pub fn export(
+        import: &GhostImport,
+        output_dir: &Path,
+        options: &MarkdownExportOptions,
+    ) -> Result<MarkdownExportReport> {
+        let mut report = MarkdownExportReport::default();
+        let mut fetcher = ImageFetcher::new(output_dir, options);
+
+        for post in &import.data.posts {
+            let (dir, image_prefix) = if post.r#type == "page" {
+                (output_dir.join(PAGES_DIR), "../")
+            } else {
+                (output_dir.to_path_buf(), "")
+            };
+
+            let mut image_urls = HashMap::new();
+            for src in Self::image_sources(post) {
+                match fetcher.fetch(&src) {
+                    Ok(Some(path)) => {
+                        image_urls.insert(src, format!("{}{}", image_prefix, path));
+                    }
+                    Ok(None) => {}
+                    Err(_) => report.missing_images.push((post.slug.clone(), src)),
+                }
+            }
+
+            let path = dir.join(Self::file_name(post));
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create directory {:?}", dir))?;
+            std::fs::write(&path, Self::to_markdown(import, post, &image_urls)?)
+                .with_context(|| format!("Failed to write {:?}", path))?;
+            report.files.push(path);
+        }
+
+        report.images = fetcher.written;
+        Ok(report)
+    }
+
+    /// `<slug>.md`, or `<id>.md` for a post without one. A slug that is not
+    /// a single plain path component, such as `../x`, is slugified again so
+    /// the file stays inside the output directory.
+    fn file_name(post: &Post) -> String {
+        let mut components = Path::new(&post.slug).components();
+        let plain = matches!(components.next(), Some(Component::Normal(_)))
+            && components.next().is_none()
+            && !post.slug.contains(['/', '\\']);
+        let slug = if plain {
+            post.slug.clone()
+        } else {
+            SlugStyle::default().slugify(&post.slug)
+        };
+        if slug.is_empty() {
+            format!("{}.md", post.id)
+        } else {
+            format!("{}.md", slug)
+        }
+    }
+
+    /// Renders one post as a Markdown document with frontmatter.
+    pub fn to_markdown(
+        import: &GhostImport,
+        post: &Post,
+        image_urls: &HashMap<String, String>,
+    ) -> Result<String> {
+        let writer = MarkdownWriter::new(image_urls);
+        let body = match (&post.html, &post.lexical, &post.mobiledoc) {
+            (Some(html), _, _) if !html.trim().is_empty() => writer.from_html(html),
+            (_, Some(lexical), _) => writer.from_lexical(lexical),
+            (_, _, Some(mobiledoc)) => Self::mobiledoc_markdown(mobiledoc),
+            _ => String::new(),
+        };
+        // Images inside raw HTML blocks are not seen by the writer.
+        let body = MarkdownProcessor::rewrite_image_urls(&body, image_urls);
+
+        let frontmatter = serde_yaml::to_string(&Self::frontmatter(import, post, image_urls))?;
+        Ok(format!("---\n{}---\n\n{}", frontmatter, body))
+    }
+
+    fn frontmatter(
+        import: &GhostImport,
+        post: &Post,
+        image_urls: &HashMap<String, String>,
+    ) -> Mapping {
+        let mut frontmatter = Mapping::new();
+        let mut set = |key: &str, value: YamlValue| {
+            frontmatter.insert(YamlValue::from(key), value);
+        };
+
+        set("title", post.title.clone().into());
+        if !post.slug.is_empty() {
+            set("slug", post.slug.clone().into());
+        }
+        if let Some(published_at) = post.published_at {
+            set(
+                "date",
+                published_at
+                    .to_rfc3339_opts(SecondsFormat::Secs, true)
+                    .into(),
+            );
+        }
+
+        let tags: Vec<YamlValue> = import
+            .data
+            .posts_tags
+            .iter()
+            .filter(|pt| pt.post_id == post.id)
+            .filter_map(|pt| import.data.tags.iter().find(|t| t.id == pt.tag_id))
+            .map(|tag| tag.name.clone().into())
+            .collect();
+        if !tags.is_empty() {
+            set("tags", YamlValue::Sequence(tags));
+        }
+
+        let mut author_ids: Vec<i32> = import
+            .data
+            .posts_authors
+            .iter()
+            .filter(|pa| pa.post_id == post.id)
+            .map(|pa| pa.author_id)
+            .collect();
+        if author_ids.is_empty() {
+            author_ids.extend(post.author_id);
+        }
+        let authors: Vec<YamlValue> = author_ids
+            .iter()
+            .filter_map(|id| import.data.users.iter().find(|u| u.id == *id))
+            .map(|user| user.name.clone().into())
+            .collect();
+        if !authors.is_empty() {
+            set("authors", YamlValue::Sequence(authors));
+        }
+
+        // A future date alone makes the importer schedule the post again
+        match post.status.as_str() {
+            "published" | "scheduled" => {}
+            "draft" => set("draft", true.into()),
+            status => set("status", status.into()),
+        }
+        if post.visibility != PostVisibility::Public.name() {
+            set("visibility", post.visibility.clone().into());
+        }
+        let tiers: Vec<YamlValue> = import
+            .data
+            .posts_products
+            .iter()
+            .filter(|pp| pp.post_id == post.id)
+            .filter_map(|pp| import.data.products.iter().find(|p| p.id == pp.product_id))
+            .map(|product| product.name.clone().into())
+            .collect();
+        if !tiers.is_empty() {
+            set("tiers", YamlValue::Sequence(tiers));
+        }
+        if post.featured == Some(1) {
+            set("featured", true.into());
+        }
+        if let Some(image) = &post.feature_image {
+            let image = image_urls.get(image).unwrap_or(image);
+            set("image", image.clone().into());
+        }
+        if let Some(excerpt) = &post.custom_excerpt {
+            set("description", excerpt.clone().into());
+        }
+        if let Some(url) = &post.canonical_url {
+            set("canonical_url", url.clone().into());
+        }
+
+        // Social fields equal to what the import derives anyway are left out
+        let meta = import.data.posts_meta.iter().find(|m| m.post_id == post.id);
+        let meta = meta.map(|m| {
+            let title = m.meta_title.as_ref().or(Some(&post.title));
+            let description = m.meta_description.as_ref().or(post.custom_excerpt.as_ref());
+            let image = post.feature_image.as_ref();
+            [
+                ("meta_title", m.meta_title.as_ref(), None),
+                ("meta_description", m.meta_description.as_ref(), None),
+                ("og_title", m.og_title.as_ref(), title),
+                ("og_description", m.og_description.as_ref(), description),
+                ("og_image", m.og_image.as_ref(), image),
+                ("twitter_title", m.twitter_title.as_ref(), m.og_title.as_ref().or(title)),
+                ("twitter_description", m.twitter_description.as_ref(), m.og_description.as_ref().or(description)),
+                ("twitter_image", m.twitter_image.as_ref(), m.og_image.as_ref().or(image)),
+                ("email_subject", m.email_subject.as_ref(), None),
+                ("feature_image_alt", m.feature_image_alt.as_ref(), None),
+                ("feature_image_caption", m.feature_image_caption.as_ref(), None),
+            ]
+        });
+        // Ghost 2.x kept the SEO fields on the post
+        let flat = [
+            ("meta_title", post.meta_title.as_ref(), None),
+            ("meta_description", post.meta_description.as_ref(), None),
+        ];
+        for (key, value, derived) in meta.into_iter().flatten().chain(flat) {
+            if let Some(value) = value.filter(|value| Some(*value) != derived) {
+                set(key, value.clone().into());
+            }
+        }
+
+        frontmatter
+    }
+
+    /// Every image a post shows, including its feature image.
+    fn image_sources(post: &Post) -> Vec<String> {
+        let mut sources: Vec<String> = post.feature_image.iter().cloned().collect();
+
+        if let Some(html) = post.html.as_deref().filter(|html| !html.trim().is_empty()) {
+            let selector = Selector::parse("img[src]").unwrap();
+            let fragment = Html::parse_fragment(html);
+            sources.extend(
+                fragment
+                    .select(&selector)
+                    .filter_map(|img| img.value().attr("src"))
+                    .map(str::to_string),
+            );
+        } else if let Some(lexical) = &post.lexical {
+            if let Ok(document) = serde_json::from_str::<Value>(lexical) {
+                collect_lexical_images(&document["root"], &mut sources);
+            }
+        }
+
+        sources.sort();
+        sources.dedup();
+        sources
+    }
+
+    /// Mobiledoc documents gmi writes keep the Markdown in markdown cards.
+    fn mobiledoc_markdown(mobiledoc: &str) -> String {
+        let Ok(document) = serde_json::from_str::<Value>(mobiledoc) else {
+            return String::new();
+        };
+        let cards: Vec<&str> = document["cards"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|card| card[0] == "markdown")
+            .filter_map(|card| card[1]["markdown"].as_str())
+            .collect();
+        cards.join("\n\n")
+    }
+}
+
+fn collect_lexical_images(node: &Value, sources: &mut Vec<String>) {
+    if node["type"] == "image" {
+        if let Some(src) = node["src"].as_str() {
+            sources.push(src.to_string());
+        }
+    }
+    for child in node["children"].as_array().into_iter().flatten() {
+        collect_lexical_images(child, sources);
+    }
+}
+
+/// Copies or downloads images hosted by the Ghost site into the output tree.
+struct ImageFetcher<'a> {
+    output_dir: &'a Path,
+    options: &'a MarkdownExportOptions,
+    agent: ureq::Agent,
+    /// Path below `content/images/` -> path relative to the output root
+    fetched: HashMap<String, String>,
+    written: Vec<PathBuf>,
+}
+
+impl<'a> ImageFetcher<'a> {
+    fn new(output_dir: &'a Path, options: &'a MarkdownExportOptions) -> Self {
+        Self {
+            output_dir,
+            options,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(60))
+                .build(),
+            fetched: HashMap::new(),
+            written: Vec::new(),
+        }
+    }
+
+    /// Returns the local path of a site image, `None` for images hosted
+    /// elsewhere, and an error when a site image could not be fetched.
+    fn fetch(&mut self, src: &str) -> Result<Option<String>> {
+        let Some(site_path) = self.site_image_path(src) else {
+            return Ok(None);
+        };
+        let relative = percent_decode(&site_path);
+        if let Some(path) = self.fetched.get(&relative) {
+            return Ok(Some(path.clone()));
+        }
+
+        if Path::new(&relative)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            anyhow::bail!("Refusing to write image outside the output: {}", src);
+        }
+        let local = format!("{}/{}", IMAGES_DIR, relative);
+        let destination = self.output_dir.join(&local);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let copied = self.options.content_dir.as_ref().and_then(|dir| {
+            [dir.join("images").join(&relative), dir.join(&relative)]
+                .into_iter()
+                .find(|candidate| candidate.is_file())
+        });
+        match (copied, &self.options.site_url) {
+            (Some(file), _) => {
+                std::fs::copy(&file, &destination)
+                    .with_context(|| format!("Failed to copy {:?}", file))?;
+            }
+            (None, Some(site_url)) => {
+                let url = format!(
+                    "{}/content/images/{}",
+                    site_url.trim_end_matches('/'),
+                    site_path
+                );
+                let response = self
+                    .agent
+                    .get(&url)
+                    .call()
+                    .with_context(|| format!("Failed to download {}", url))?;
+                let mut data = Vec::new();
+                response.into_reader().read_to_end(&mut data)?;
+                std::fs::write(&destination, data)?;
+            }
+            (None, None) => anyhow::bail!("No --content-dir or --site-url to fetch {} from", src),
+        }
+
+        self.written.push(destination);
+        self.fetched.insert(relative, local.clone());
+        Ok(Some(local))
+    }
+
+    /// Path below `content/images/` for images stored by the site. Resized
+    /// variants (`size/w600/...`) resolve to the original upload.
+    fn site_image_path(&self, src: &str) -> Option<String> {
+        let site_url = self
+            .options
+            .site_url
+            .as_deref()
+            .map(|s| s.trim_end_matches('/'));
+        let path = src
+            .strip_prefix("__GHOST_URL__")
+            .or_else(|| site_url.and_then(|site| src.strip_prefix(site)))
+            .or_else(|| src.starts_with("/content/").then_some(src))?;
+        let path = path
+            .split(['?', '#'])
+            .next()?
+            .strip_prefix("/content/images/")?;
+
+        let path = match path.strip_prefix("size/") {
+            Some(sized) => sized.split_once('/')?.1,
+            None => path,
+        };
+        (!path.is_empty()).then(|| path.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::GhostReader;
+    use serde_json::json;
+
+    fn import() -> GhostImport {
+        let export = json!({
+            "meta": { "exported_on": 0, "version": "5.75.1" },
+            "data": {
+                "posts": [
+                    {
+                        "id": "p1", "title": "Hello: World", "slug": "hello",
+                        "html": "<p>Hi <strong>there</strong></p><figure><img src=\"__GHOST_URL__/content/images/size/w600/2024/01/a%20b.png\" alt=\"A\"></figure><p><img src=\"https://elsewhere.org/x.png\"></p>",
+                        "feature_image": "__GHOST_URL__/content/images/2024/01/a%20b.png",
+                        "featured": true, "status": "published", "type": "post", "visibility": "public",
+                        "custom_excerpt": "Short",
+                        "published_at": "2024-01-02T03:04:05.000Z",
+                        "created_at": "2024-01-01T00:00:00.000Z", "updated_at": "2024-01-01T00:00:00.000Z"
+                    },
+                    {
+                        "id": "p2", "title": "About", "slug": "about",
+                        "lexical": "{\"root\":{\"children\":[{\"type\":\"paragraph\",\"children\":[{\"type\":\"extended-text\",\"text\":\"Me\",\"format\":0}]}]}}",
+                        "status": "draft", "type": "page", "visibility": "tiers",
+                        "created_at": "2024-01-01T00:00:00.000Z", "updated_at": "2024-01-01T00:00:00.000Z"
+                    }
+                ],
+                "tags": [{ "id": "t1", "name": "Rust", "slug": "rust", "created_at": "2024-01-01T00:00:00.000Z" }],
+                "users": [{ "id": "u1", "name": "Jane", "slug": "jane", "email": "j@x.org", "created_at": "2024-01-01T00:00:00.000Z" }],
+                "posts_tags": [{ "id": "1", "post_id": "p1", "tag_id": "t1" }],
+                "posts_authors": [{ "id": "1", "post_id": "p1", "author_id": "u1" }],
+                "products": [{ "id": "g1", "name": "Gold", "slug": "gold", "created_at": "2024-01-01T00:00:00.000Z" }],
+                "posts_products": [{ "id": "1", "post_id": "p2", "product_id": "g1" }],
+                "posts_meta": [{ "id": "1", "post_id": "p1", "meta_title": "Hello SEO", "og_title": "Hello SEO", "twitter_title": "Hi on Twitter", "og_description": "Short" }]
+            }
+        });
+        GhostReader::parse(&export.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_export_markdown_tree() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let content_dir = temp_dir.path().join("content");
+        std::fs::create_dir_all(content_dir.join("images/2024/01")).unwrap();
+        std::fs::write(content_dir.join("images/2024/01/a b.png"), b"png").unwrap();
+        let output = temp_dir.path().join("out");
+
+        let options = MarkdownExportOptions {
+            content_dir: Some(content_dir),
+            site_url: None,
+        };
+        let report = MarkdownExporter::export(&import(), &output, &options).unwrap();
+
+        assert_eq!(
+            report.files,
+            vec![output.join("hello.md"), output.join("pages/about.md")]
+        );
+        assert_eq!(report.images, vec![output.join("images/2024/01/a b.png")]);
+        assert!(report.missing_images.is_empty());
+
+        let post = std::fs::read_to_string(output.join("hello.md")).unwrap();
+        assert_eq!(
+            post,
+            concat!(
+                "---\n",
+                "title: 'Hello: World'\n",
+                "slug: hello\n",
+                "date: 2024-01-02T03:04:05Z\n",
+                "tags:\n- Rust\n",
+                "authors:\n- Jane\n",
+                "featured: true\n",
+                "image: images/2024/01/a b.png\n",
+                "description: Short\n",
+                "meta_title: Hello SEO\n",
+                "twitter_title: Hi on Twitter\n",
+                "---\n\n",
+                "Hi **there**\n\n",
+                "![A](<images/2024/01/a b.png>)\n\n",
+                "![](https://elsewhere.org/x.png)\n"
+            )
+        );
+
+        let page = std::fs::read_to_string(output.join("pages/about.md")).unwrap();
+        assert!(page.contains("draft: true\nvisibility: tiers\ntiers:\n- Gold\n"));
+        assert!(page.ends_with("---\n\nMe\n"));
+
+        // The written files are read back by the importer
+        let processed = MarkdownProcessor::process_file(&output.join("hello.md")).unwrap();
+        assert_eq!(processed.frontmatter.title.as_deref(), Some("Hello: World"));
+        assert_eq!(processed.frontmatter.tags, Some(vec!["Rust".to_string()]));
+        assert_eq!(processed.frontmatter.featured, Some(true));
+    }
+
+    #[test]
+    fn test_slugs_stay_inside_the_output() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("out");
+        let mut import = import();
+        import.data.posts[0].slug = "../../.bashrc".to_string();
+        import.data.posts[1].slug = "..".to_string();
+
+        let report =
+            MarkdownExporter::export(&import, &output, &MarkdownExportOptions::default()).unwrap();
+        assert_eq!(
+            report.files,
+            vec![output.join("bashrc.md"), output.join(format!("pages/{}.md", import.data.posts[1].id))]
+        );
+        assert!(!temp_dir.path().join(".bashrc.md").exists());
+    }
+
+    #[test]
+    fn test_missing_images_are_reported() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let report = MarkdownExporter::export(
+            &import(),
+            temp_dir.path(),
+            &MarkdownExportOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.missing_images.len(), 2);
+        let post = std::fs::read_to_string(temp_dir.path().join("hello.md")).unwrap();
+        assert!(post.contains("image: __GHOST_URL__/content/images/2024/01/a%20b.png"));
+    }
+}
@@ -0,0 +1,636 @@
+use crate::processors::MarkdownProcessor;
+use scraper::{ElementRef, Html, Node};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const FORMAT_BOLD: u64 = 1;
+const FORMAT_ITALIC: u64 = 1 << 1;
+const FORMAT_STRIKETHROUGH: u64 = 1 << 2;
+const FORMAT_CODE: u64 = 1 << 4;
+
+/// Elements kept as raw HTML because Markdown has no equivalent for them.
+const RAW_BLOCKS: [&str; 13] = [
+    "table", "iframe", "video", "audio", "script", "style", "details", "form", "svg", "embed",
+    "object", "dl", "canvas",
+];
+
+/// Inline elements kept as raw HTML for the same reason.
+const RAW_INLINE: [&str; 8] = ["sup", "sub", "u", "mark", "small", "kbd", "abbr", "q"];
+
+/// Turns Ghost post content (HTML or a Lexical document) back into Markdown.
+///
+/// Paragraphs, headings, emphasis, links, lists, quotes, code and images map
+/// onto Markdown; Ghost cards and anything else Markdown cannot express stay
+/// as raw HTML blocks, which Markdown renderers pass through untouched.
+pub struct MarkdownWriter<'a> {
+    image_urls: &'a HashMap<String, String>,
+}
+
+impl<'a> MarkdownWriter<'a> {
+    /// `image_urls` maps image sources found in the content to the references
+    /// the Markdown should use instead.
+    pub fn new(image_urls: &'a HashMap<String, String>) -> Self {
+        Self { image_urls }
+    }
+
+    pub fn from_html(&self, html: &str) -> String {
+        let fragment = Html::parse_fragment(html);
+        finish(self.html_blocks(fragment.root_element()))
+    }
+
+    pub fn from_lexical(&self, lexical: &str) -> String {
+        let Ok(document) = serde_json::from_str::<Value>(lexical) else {
+            return String::new();
+        };
+        finish(self.lexical_blocks(&document["root"]["children"]))
+    }
+
+    fn image(&self, src: &str, alt: &str, title: &str) -> String {
+        let src = self.image_urls.get(src).map_or(src, String::as_str);
+        let title = if title.is_empty() {
+            String::new()
+        } else {
+            format!(" \"{}\"", title.replace('"', "\\\""))
+        };
+        format!("![{}]({}{})", escape(alt), destination(src), title)
+    }
+
+    fn html_blocks(&self, parent: ElementRef) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+
+        for child in parent.children() {
+            match child.value() {
+                Node::Text(text) => inline.push_str(&escape(&collapse_whitespace(text))),
+                Node::Comment(comment)
+                    if MarkdownProcessor::is_paywall_marker(&format!("<!--{}-->", &**comment)) =>
+                {
+                    flush_paragraph(&mut inline, &mut blocks);
+                    blocks.push(MarkdownProcessor::PAYWALL_MARKER.to_string());
+                }
+                Node::Element(_) => {
+                    let element = ElementRef::wrap(child).expect("element node");
+                    if is_inline(element) {
+                        inline.push_str(&self.html_inline_element(element));
+                        continue;
+                    }
+                    flush_paragraph(&mut inline, &mut blocks);
+                    if let Some(block) = self.html_block(element) {
+                        blocks.push(block);
+                    }
+                }
+                _ => {}
+            }
+        }
+        flush_paragraph(&mut inline, &mut blocks);
+        blocks
+    }
+
+    fn html_block(&self, element: ElementRef) -> Option<String> {
+        let name = element.value().name();
+        let block = match name {
+            "p" => self.html_inline(element).trim().to_string(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(2);
+                format!("{} {}", "#".repeat(level), self.html_inline(element).trim())
+            }
+            "blockquote" => quote(&finish(self.html_blocks(element))),
+            "ul" | "ol" => self.html_list(element),
+            "pre" => {
+                let code = element
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .find(|child| child.value().name() == "code");
+                let language = code
+                    .and_then(|code| code.value().attr("class"))
+                    .and_then(|class| {
+                        class.split_whitespace().find_map(|c| {
+                            c.strip_prefix("language-")
+                                .or_else(|| c.strip_prefix("lang-"))
+                        })
+                    })
+                    .unwrap_or("");
+                code_block(&element.text().collect::<String>(), language)
+            }
+            "hr" => "---".to_string(),
+            "img" => self.html_image(element),
+            "figure" => self.html_figure(element),
+            "div" if is_card(element) => element.html(),
+            _ if RAW_BLOCKS.contains(&name) => element.html(),
+            _ => finish(self.html_blocks(element)),
+        };
+        (!block.trim().is_empty()).then_some(block)
+    }
+
+    fn html_figure(&self, figure: ElementRef) -> String {
+        let Some(image) = descendants(figure).find(|e| e.value().name() == "img") else {
+            return figure.html();
+        };
+        if descendants(figure).any(|e| RAW_BLOCKS.contains(&e.value().name()))
+            || is_card(figure) && !has_class(figure, "kg-image-card")
+        {
+            return figure.html();
+        }
+
+        let mut image_md = self.html_image(image);
+        if let Some(link) = descendants(figure).find(|e| e.value().name() == "a") {
+            if let Some(href) = link.value().attr("href") {
+                image_md = format!("[{}]({})", image_md, destination(href));
+            }
+        }
+        let caption = descendants(figure)
+            .find(|e| e.value().name() == "figcaption")
+            .map(|caption| self.html_inline(caption).trim().to_string())
+            .unwrap_or_default();
+        if caption.is_empty() {
+            image_md
+        } else {
+            format!("{}\n\n*{}*", image_md, caption)
+        }
+    }
+
+    fn html_image(&self, image: ElementRef) -> String {
+        let attr = |name| image.value().attr(name).unwrap_or("");
+        self.image(attr("src"), attr("alt"), attr("title"))
+    }
+
+    fn html_list(&self, list: ElementRef) -> String {
+        let ordered = list.value().name() == "ol";
+        let start = list
+            .value()
+            .attr("start")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(1);
+        let items: Vec<ElementRef> = list
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| child.value().name() == "li")
+            .collect();
+        let loose = items.iter().any(|item| {
+            item.children()
+                .filter_map(ElementRef::wrap)
+                .any(|c| c.value().name() == "p")
+        });
+
+        let rendered: Vec<String> = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let marker = if ordered {
+                    format!("{}. ", start + index)
+                } else {
+                    "- ".to_string()
+                };
+                let separator = if loose { "\n\n" } else { "\n" };
+                list_item(&marker, &self.html_blocks(*item).join(separator))
+            })
+            .collect();
+        rendered.join(if loose { "\n\n" } else { "\n" })
+    }
+
+    fn html_inline(&self, element: ElementRef) -> String {
+        let mut out = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => out.push_str(&escape(&collapse_whitespace(text))),
+                Node::Element(_) => {
+                    let element = ElementRef::wrap(child).expect("element node");
+                    out.push_str(&self.html_inline_element(element));
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+
+    fn html_inline_element(&self, element: ElementRef) -> String {
+        let name = element.value().name();
+        match name {
+            "strong" | "b" => wrap(&self.html_inline(element), "**"),
+            "em" | "i" => wrap(&self.html_inline(element), "*"),
+            "s" | "del" | "strike" => wrap(&self.html_inline(element), "~~"),
+            "code" => inline_code(&element.text().collect::<String>()),
+            "br" => "\\\n".to_string(),
+            "img" => self.html_image(element),
+            "a" => {
+                let text = self.html_inline(element);
+                match element.value().attr("href") {
+                    Some(href) if !text.trim().is_empty() => {
+                        let title = element
+                            .value()
+                            .attr("title")
+                            .map(|t| format!(" \"{}\"", t.replace('"', "\\\"")))
+                            .unwrap_or_default();
+                        format!("[{}]({}{})", text.trim(), destination(href), title)
+                    }
+                    _ => text,
+                }
+            }
+            _ if RAW_INLINE.contains(&name) => element.html(),
+            _ => self.html_inline(element),
+        }
+    }
+
+    fn lexical_blocks(&self, nodes: &Value) -> Vec<String> {
+        nodes
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|node| {
+                let block = self.lexical_block(node);
+                (!block.trim().is_empty()).then_some(block)
+            })
+            .collect()
+    }
+
+    fn lexical_block(&self, node: &Value) -> String {
+        let text = |field: &str| node[field].as_str().unwrap_or("").to_string();
+        match node["type"].as_str().unwrap_or("") {
+            "paragraph" => self.lexical_inline(&node["children"]).trim().to_string(),
+            "heading" | "extended-heading" => {
+                let tag = text("tag");
+                let level = tag.get(1..).and_then(|l| l.parse().ok()).unwrap_or(2);
+                format!(
+                    "{} {}",
+                    "#".repeat(level),
+                    self.lexical_inline(&node["children"]).trim()
+                )
+            }
+            "quote" | "extended-quote" | "aside" => {
+                quote(self.lexical_inline(&node["children"]).trim())
+            }
+            "list" => self.lexical_list(node),
+            "codeblock" => code_block(&text("code"), &text("language")),
+            "image" => {
+                let mut image = self.image(&text("src"), &text("alt"), &text("title"));
+                if !text("href").is_empty() {
+                    image = format!("[{}]({})", image, destination(&text("href")));
+                }
+                let caption = strip_tags(&text("caption"));
+                if caption.trim().is_empty() {
+                    image
+                } else {
+                    format!("{}\n\n*{}*", image, escape(caption.trim()))
+                }
+            }
+            "markdown" => text("markdown").trim_end().to_string(),
+            "html" => text("html"),
+            "horizontalrule" => "---".to_string(),
+            "paywall" => MarkdownProcessor::PAYWALL_MARKER.to_string(),
+            other => match node["html"].as_str() {
+                Some(html) => html.to_string(),
+                None => format!("<!-- unsupported {} card -->", other),
+            },
+        }
+    }
+
+    fn lexical_list(&self, list: &Value) -> String {
+        let ordered = list["listType"] == "number" || list["tag"] == "ol";
+        let start = list["start"].as_u64().unwrap_or(1) as usize;
+        list["children"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, item)| {
+                let marker = if ordered {
+                    format!("{}. ", start + index)
+                } else {
+                    "- ".to_string()
+                };
+                let (nested, inline): (Vec<&Value>, Vec<&Value>) = item["children"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .partition(|child| child["type"] == "list");
+                let mut parts = vec![self
+                    .lexical_inline(&Value::Array(inline.into_iter().cloned().collect()))
+                    .trim()
+                    .to_string()];
+                parts.extend(nested.into_iter().map(|list| self.lexical_list(list)));
+                list_item(&marker, &parts.join("\n"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn lexical_inline(&self, nodes: &Value) -> String {
+        let mut out = String::new();
+        for node in nodes.as_array().into_iter().flatten() {
+            match node["type"].as_str().unwrap_or("") {
+                "text" | "extended-text" => {
+                    let text = node["text"].as_str().unwrap_or("");
+                    let format = node["format"].as_u64().unwrap_or(0);
+                    let mut text = if format & FORMAT_CODE != 0 {
+                        inline_code(text)
+                    } else {
+                        escape(text)
+                    };
+                    for (bit, marker) in [
+                        (FORMAT_STRIKETHROUGH, "~~"),
+                        (FORMAT_ITALIC, "*"),
+                        (FORMAT_BOLD, "**"),
+                    ] {
+                        if format & bit != 0 {
+                            text = wrap(&text, marker);
+                        }
+                    }
+                    out.push_str(&text);
+                }
+                "link" | "autolink" => {
+                    let text = self.lexical_inline(&node["children"]);
+                    let url = node["url"].as_str().unwrap_or("");
+                    out.push_str(&format!("[{}]({})", text.trim(), destination(url)));
+                }
+                "linebreak" => out.push_str("\\\n"),
+                _ => out.push_str(&self.lexical_inline(&node["children"])),
+            }
+        }
+        out
+    }
+}
+
+fn finish(blocks: Vec<String>) -> String {
+    let mut markdown = blocks.join("\n\n");
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+fn flush_paragraph(inline: &mut String, blocks: &mut Vec<String>) {
+    let paragraph = inline.trim();
+    if !paragraph.is_empty() {
+        blocks.push(paragraph.to_string());
+    }
+    inline.clear();
+}
+
+fn is_inline(element: ElementRef) -> bool {
+    matches!(
+        element.value().name(),
+        "a" | "strong"
+            | "b"
+            | "em"
+            | "i"
+            | "s"
+            | "del"
+            | "strike"
+            | "code"
+            | "br"
+            | "span"
+            | "sup"
+            | "sub"
+            | "u"
+            | "mark"
+            | "small"
+            | "kbd"
+            | "abbr"
+            | "q"
+    )
+}
+
+/// Ghost renders cards (bookmarks, galleries, callouts, ...) as elements with
+/// `kg-` classes; they only survive a round trip as HTML.
+fn is_card(element: ElementRef) -> bool {
+    element
+        .value()
+        .attr("class")
+        .is_some_and(|class| class.split_whitespace().any(|c| c.starts_with("kg-")))
+}
+
+fn has_class(element: ElementRef, name: &str) -> bool {
+    element
+        .value()
+        .attr("class")
+        .is_some_and(|class| class.split_whitespace().any(|c| c == name))
+}
+
+fn descendants(element: ElementRef) -> impl Iterator<Item = ElementRef> {
+    element.descendants().filter_map(ElementRef::wrap)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out
+}
+
+/// Escapes characters that would otherwise start Markdown syntax. Underscores
+/// inside words are left alone since they never open emphasis.
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let escape = match c {
+            '\\' | '`' | '*' | '[' | ']' | '<' => true,
+            '_' => {
+                let before = i > 0 && chars[i - 1].is_alphanumeric();
+                let after = chars.get(i + 1).is_some_and(|c| c.is_alphanumeric());
+                !(before && after)
+            }
+            '#' | '>' | '-' | '+' => i == 0,
+            _ => false,
+        };
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Wraps text in emphasis markers, keeping surrounding spaces outside them.
+fn wrap(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+fn inline_code(code: &str) -> String {
+    let mut fence = "`".to_string();
+    while code.contains(&fence) {
+        fence.push('`');
+    }
+    let padding = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", fence, padding, code, padding, fence)
+}
+
+fn code_block(code: &str, language: &str) -> String {
+    let mut fence = "```".to_string();
+    while code.contains(&fence) {
+        fence.push('`');
+    }
+    format!(
+        "{}{}\n{}\n{}",
+        fence,
+        language,
+        code.strip_suffix('\n').unwrap_or(code),
+        fence
+    )
+}
+
+fn destination(url: &str) -> String {
+    if url.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+fn quote(markdown: &str) -> String {
+    markdown
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Prefixes the first line with the list marker and indents the rest so they
+/// stay inside the item.
+fn list_item(marker: &str, content: &str) -> String {
+    let indent = " ".repeat(marker.len());
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match (i, line.is_empty()) {
+            (0, _) => format!("{}{}", marker, line),
+            (_, true) => String::new(),
+            _ => format!("{}{}", indent, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn strip_tags(html: &str) -> String {
+    Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .collect::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let urls = HashMap::from([(
+            "__GHOST_URL__/content/images/a.png".to_string(),
+            "images/a.png".to_string(),
+        )]);
+        let html = concat!(
+            "<h2>Title</h2>",
+            "<p>Plain <strong>bold</strong> and <em>soft</em> with <a href=\"https://x.org\">a link</a> and <code>x_y</code>.</p>",
+            "<ul><li>one</li><li>two<ul><li>nested</li></ul></li></ul>",
+            "<ol start=\"3\"><li>three</li></ol>",
+            "<blockquote><p>Quoted *text*</p></blockquote>",
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>",
+            "<figure class=\"kg-card kg-image-card kg-card-hascaption\"><img src=\"__GHOST_URL__/content/images/a.png\" alt=\"An image\"><figcaption>A caption</figcaption></figure>",
+            "<hr>",
+            "<!--members-only-->",
+            "<!--kg-card-begin: html--><table><tr><td>1</td></tr></table><!--kg-card-end: html-->",
+            "<div class=\"kg-card kg-callout-card\"><div>Note</div></div>",
+        );
+
+        let markdown = MarkdownWriter::new(&urls).from_html(html);
+
+        assert_eq!(
+            markdown,
+            concat!(
+                "## Title\n\n",
+                "Plain **bold** and *soft* with [a link](https://x.org) and `x_y`.\n\n",
+                "- one\n- two\n  - nested\n\n",
+                "3. three\n\n",
+                "> Quoted \\*text\\*\n\n",
+                "```rust\nfn main() {}\n```\n\n",
+                "![An image](images/a.png)\n\n*A caption*\n\n",
+                "---\n\n",
+                "<!--members-only-->\n\n",
+                "<table><tbody><tr><td>1</td></tr></tbody></table>\n\n",
+                "<div class=\"kg-card kg-callout-card\"><div>Note</div></div>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_lexical_to_markdown() {
+        let lexical = serde_json::json!({
+            "root": { "children": [
+                { "type": "extended-heading", "tag": "h3", "children": [{ "type": "extended-text", "text": "Hi", "format": 0 }] },
+                { "type": "paragraph", "children": [
+                    { "type": "extended-text", "text": "bold", "format": 1 },
+                    { "type": "extended-text", "text": " and ", "format": 0 },
+                    { "type": "link", "url": "https://x.org", "children": [{ "type": "extended-text", "text": "code", "format": 16 }] },
+                    { "type": "linebreak" },
+                    { "type": "extended-text", "text": "next", "format": 3 }
+                ]},
+                { "type": "list", "listType": "number", "start": 1, "children": [
+                    { "type": "listitem", "children": [
+                        { "type": "extended-text", "text": "a", "format": 0 },
+                        { "type": "list", "listType": "bullet", "children": [
+                            { "type": "listitem", "children": [{ "type": "extended-text", "text": "b", "format": 0 }] }
+                        ]}
+                    ]}
+                ]},
+                { "type": "codeblock", "code": "x = 1", "language": "py" },
+                { "type": "image", "src": "https://cdn/a.png", "alt": "", "title": "", "caption": "<span>Cap</span>" },
+                { "type": "markdown", "markdown": "| a |\n|---|\n" },
+                { "type": "paywall" },
+                { "type": "bookmark", "url": "https://x.org" }
+            ]}
+        });
+
+        let urls = HashMap::new();
+        let markdown = MarkdownWriter::new(&urls).from_lexical(&lexical.to_string());
+
+        assert_eq!(
+            markdown,
+            concat!(
+                "### Hi\n\n",
+                "**bold** and [`code`](https://x.org)\\\n***next***\n\n",
+                "1. a\n   - b\n\n",
+                "```py\nx = 1\n```\n\n",
+                "![](https://cdn/a.png)\n\n*Cap*\n\n",
+                "| a |\n|---|\n\n",
+                "<!--members-only-->\n\n",
+                "<!-- unsupported bookmark card -->\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("snake_case and _under_"),
+            "snake_case and \\_under\\_"
+        );
+        assert_eq!(escape("# not a heading"), "\\# not a heading");
+        assert_eq!(escape("a [b] <c>"), "a \\[b\\] \\<c>");
+    }
+}
@@ -0,0 +1,445 @@
+use crate::models::ghost::{
+    Data, GhostImport, PostsAuthors, PostsMeta, PostsProducts, PostsTags, RolesUsers, StaffRole,
+    User,
+};
+use crate::processors::{SlugKind, SlugRegistry, DEFAULT_AUTHOR};
+use std::collections::{HashMap, HashSet};
+
+/// What to do with a new post whose slug is already taken by an existing one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SlugConflict {
+    /// Keep the existing post and drop the new one
+    #[default]
+    Skip,
+    /// Add the new post under a free slug such as `hello-2`
+    Rename,
+    /// Replace the existing post, keeping its id
+    Overwrite,
+}
+
+/// What happened while merging new posts into an existing export.
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    pub added: usize,
+    pub tags_reused: usize,
+    pub users_reused: usize,
+    pub skipped: Vec<String>,
+    /// Old slug -> new slug
+    pub renamed: Vec<(String, String)>,
+    pub overwritten: Vec<String>,
+}
+
+/// Merges a generated import into an existing site export.
+///
+/// Tags and tiers are matched by slug and users by slug or email, so posts
+/// point at the records the site already has. Tags and users no merged post
+/// uses are left out, and posts by the exporter's default author go to the
+/// site owner. Everything new gets ids past the ones already in use, and the
+/// result is a single import holding both.
+pub struct ImportMerger;
+
+impl ImportMerger {
+    pub fn merge(
+        mut base: GhostImport,
+        incoming: GhostImport,
+        on_conflict: SlugConflict,
+    ) -> (GhostImport, MergeReport) {
+        let mut report = MergeReport::default();
+        let data = &mut base.data;
+
+        let kept: HashSet<i32> = incoming
+            .data
+            .posts
+            .iter()
+            .filter(|post| {
+                on_conflict != SlugConflict::Skip || !data.posts.iter().any(|p| p.slug == post.slug)
+            })
+            .map(|post| post.id)
+            .collect();
+        let used_tags: HashSet<i32> = incoming
+            .data
+            .posts_tags
+            .iter()
+            .filter(|row| kept.contains(&row.post_id))
+            .map(|row| row.tag_id)
+            .collect();
+        let used_users: HashSet<i32> = incoming
+            .data
+            .posts_authors
+            .iter()
+            .filter(|row| kept.contains(&row.post_id))
+            .map(|row| row.author_id)
+            .chain(incoming.data.posts.iter().filter(|p| kept.contains(&p.id)).filter_map(|p| p.author_id))
+            .collect();
+        let used_products: HashSet<i32> = incoming
+            .data
+            .posts_products
+            .iter()
+            .filter(|row| kept.contains(&row.post_id))
+            .map(|row| row.product_id)
+            .collect();
+        let owner = Self::site_owner(data);
+
+        let mut tag_ids = HashMap::new();
+        let mut next_tag_id = data.tags.iter().map(|t| t.id).max().unwrap_or(0);
+        for mut tag in incoming.data.tags.into_iter().filter(|t| used_tags.contains(&t.id)) {
+            match data.tags.iter().find(|t| t.slug == tag.slug) {
+                Some(existing) => {
+                    tag_ids.insert(tag.id, existing.id);
+                    report.tags_reused += 1;
+                }
+                None => {
+                    next_tag_id += 1;
+                    tag_ids.insert(tag.id, next_tag_id);
+                    tag.id = next_tag_id;
+                    data.tags.push(tag);
+                }
+            }
+        }
+
+        let mut product_ids = HashMap::new();
+        let mut next_product_id = data.products.iter().map(|p| p.id).max().unwrap_or(0);
+        for mut product in incoming.data.products.into_iter().filter(|p| used_products.contains(&p.id)) {
+            match data.products.iter().find(|p| p.slug == product.slug) {
+                Some(existing) => {
+                    product_ids.insert(product.id, existing.id);
+                }
+                None => {
+                    next_product_id += 1;
+                    product_ids.insert(product.id, next_product_id);
+                    product.id = next_product_id;
+                    data.products.push(product);
+                }
+            }
+        }
+
+        let mut user_ids = HashMap::new();
+        let mut new_users = HashMap::new();
+        let mut next_user_id = data.users.iter().map(|u| u.id).max().unwrap_or(0);
+        for mut user in incoming.data.users.into_iter().filter(|u| used_users.contains(&u.id)) {
+            if let (Some(owner), true) = (owner, Self::is_default_author(&user)) {
+                user_ids.insert(user.id, owner);
+                continue;
+            }
+            let existing = data.users.iter().find(|u| {
+                u.slug == user.slug
+                    || (!user.email.is_empty() && u.email.eq_ignore_ascii_case(&user.email))
+            });
+            match existing {
+                Some(existing) => {
+                    user_ids.insert(user.id, existing.id);
+                    report.users_reused += 1;
+                }
+                None => {
+                    next_user_id += 1;
+                    user_ids.insert(user.id, next_user_id);
+                    new_users.insert(user.id, next_user_id);
+                    user.id = next_user_id;
+                    data.users.push(user);
+                }
+            }
+        }
+
+        let mut slugs = SlugRegistry::default();
+        for post in &data.posts {
+            slugs.reserve(SlugKind::Post, &post.slug);
+        }
+        let mut post_ids = HashMap::new();
+        let mut next_post_id = data.posts.iter().map(|p| p.id).max().unwrap_or(0);
+        for mut post in incoming.data.posts {
+            let existing = data.posts.iter().position(|p| p.slug == post.slug);
+            let old_id = post.id;
+            post.author_id = post.author_id.and_then(|id| user_ids.get(&id).copied());
+
+            match (existing, on_conflict) {
+                (Some(_), SlugConflict::Skip) => {
+                    report.skipped.push(post.slug);
+                    continue;
+                }
+                (Some(index), SlugConflict::Overwrite) => {
+                    post.id = data.posts[index].id;
+                    data.posts_tags.retain(|row| row.post_id != post.id);
+                    data.posts_authors.retain(|row| row.post_id != post.id);
+                    data.posts_products.retain(|row| row.post_id != post.id);
+                    data.posts_meta.retain(|row| row.post_id != post.id);
+                    report.overwritten.push(post.slug.clone());
+                    post_ids.insert(old_id, post.id);
+                    data.posts[index] = post;
+                    continue;
+                }
+                (Some(_), SlugConflict::Rename) => {
+                    let slug = slugs.reserve(SlugKind::Post, &post.slug);
+                    report.renamed.push((post.slug.clone(), slug.clone()));
+                    post.slug = slug;
+                }
+                (None, _) => post.slug = slugs.reserve(SlugKind::Post, &post.slug),
+            }
+
+            next_post_id += 1;
+            post.id = next_post_id;
+            post_ids.insert(old_id, post.id);
+            data.posts.push(post);
+            report.added += 1;
+        }
+
+        let mut next_id = data.posts_tags.iter().map(|r| r.id).max().unwrap_or(0);
+        for row in incoming.data.posts_tags {
+            if let (Some(&post_id), Some(&tag_id)) =
+                (post_ids.get(&row.post_id), tag_ids.get(&row.tag_id))
+            {
+                next_id += 1;
+                data.posts_tags.push(PostsTags { id: next_id, post_id, tag_id, ..row });
+            }
+        }
+
+        let mut next_id = data.posts_authors.iter().map(|r| r.id).max().unwrap_or(0);
+        for row in incoming.data.posts_authors {
+            if let (Some(&post_id), Some(&author_id)) =
+                (post_ids.get(&row.post_id), user_ids.get(&row.author_id))
+            {
+                next_id += 1;
+                data.posts_authors.push(PostsAuthors { id: next_id, post_id, author_id, ..row });
+            }
+        }
+
+        let mut next_id = data.posts_products.iter().map(|r| r.id).max().unwrap_or(0);
+        for row in incoming.data.posts_products {
+            if let (Some(&post_id), Some(&product_id)) =
+                (post_ids.get(&row.post_id), product_ids.get(&row.product_id))
+            {
+                next_id += 1;
+                data.posts_products.push(PostsProducts { id: next_id, post_id, product_id, ..row });
+            }
+        }
+
+        let mut next_id = data.posts_meta.iter().map(|r| r.id).max().unwrap_or(0);
+        for row in incoming.data.posts_meta {
+            if let Some(&post_id) = post_ids.get(&row.post_id) {
+                next_id += 1;
+                data.posts_meta.push(PostsMeta { id: next_id, post_id, ..row });
+            }
+        }
+
+        // Roles are matched by name, like Ghost's importer does
+        let mut role_ids = HashMap::new();
+        let mut next_role_id = data.roles.iter().map(|r| r.id).max().unwrap_or(0);
+        for mut role in incoming.data.roles {
+            match data.roles.iter().find(|r| r.name.eq_ignore_ascii_case(&role.name)) {
+                Some(existing) => {
+                    role_ids.insert(role.id, existing.id);
+                }
+                None => {
+                    next_role_id += 1;
+                    role_ids.insert(role.id, next_role_id);
+                    role.id = next_role_id;
+                    data.roles.push(role);
+                }
+            }
+        }
+
+        // Users that already exist keep the roles the site gave them
+        let mut next_id = data.roles_users.iter().map(|r| r.id).max().unwrap_or(0);
+        for row in incoming.data.roles_users {
+            if let (Some(&user_id), Some(&role_id)) =
+                (new_users.get(&row.user_id), role_ids.get(&row.role_id))
+            {
+                next_id += 1;
+                data.roles_users.push(RolesUsers { id: next_id, user_id, role_id });
+            }
+        }
+
+        // The posts just added follow the schema the new import targets
+        base.meta = incoming.meta;
+        (base, report)
+    }
+
+    /// The user the exporter makes up for posts without an author.
+    fn is_default_author(user: &User) -> bool {
+        user.name == DEFAULT_AUTHOR && user.email.ends_with("@example.com")
+    }
+
+    /// The site's owner, else its first administrator or first user.
+    fn site_owner(data: &Data) -> Option<i32> {
+        let holder = |role: &str| {
+            let role = data.roles.iter().find(|r| r.name == role)?;
+            data.roles_users.iter().find(|r| r.role_id == role.id).map(|r| r.user_id)
+        };
+        holder("Owner")
+            .or_else(|| holder(StaffRole::Administrator.name()))
+            .or_else(|| data.users.first().map(|u| u.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ghost::*;
+
+    fn import(posts: &[&str], tags: &[&str], users: &[(&str, &str)]) -> GhostImport {
+        let data = Data {
+            posts: posts
+                .iter()
+                .enumerate()
+                .map(|(i, slug)| Post {
+                    id: i as i32 + 1,
+                    title: slug.to_string(),
+                    slug: slug.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            tags: tags
+                .iter()
+                .enumerate()
+                .map(|(i, slug)| Tag {
+                    id: i as i32 + 1,
+                    name: slug.to_string(),
+                    slug: slug.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            users: users
+                .iter()
+                .enumerate()
+                .map(|(i, (slug, email))| User {
+                    id: i as i32 + 1,
+                    name: slug.to_string(),
+                    slug: slug.to_string(),
+                    email: email.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            posts_tags: (1..=posts.len() as i32)
+                .map(|id| PostsTags { id, post_id: id, tag_id: 1, sort_order: 0 })
+                .collect(),
+            posts_authors: (1..=posts.len() as i32)
+                .map(|id| PostsAuthors { id, post_id: id, author_id: 1, sort_order: 0 })
+                .collect(),
+            roles_users: Vec::new(),
+            roles: Vec::new(),
+            products: Vec::new(),
+            posts_products: Vec::new(),
+            posts_meta: Vec::new(),
+        };
+        GhostImport { meta: Meta::default(), data }
+    }
+
+    #[test]
+    fn test_merge_reuses_tags_and_users() {
+        let base = import(&["hello", "about"], &["news", "rust"], &[("jane", "jane@example.org")]);
+        let mut incoming = import(&["fresh"], &["rust"], &[("j", "Jane@example.org")]);
+        incoming.data.tags.push(Tag { id: 2, name: "Go".into(), slug: "go".into(), ..Default::default() });
+        incoming.data.posts_tags.push(PostsTags { id: 2, post_id: 1, tag_id: 2, sort_order: 1 });
+        incoming.data.users.push(User { id: 2, name: "Bob".into(), slug: "bob".into(), ..Default::default() });
+        incoming.data.posts_authors.push(PostsAuthors { id: 2, post_id: 1, author_id: 2, sort_order: 1 });
+        // Unused, like the placeholder tag the exporter adds
+        incoming.data.tags.push(Tag { id: 3, name: "General".into(), slug: "general".into(), ..Default::default() });
+        incoming.data.roles = vec![Role::new(1, StaffRole::Author), Role::new(2, StaffRole::Editor)];
+        incoming.data.roles_users = vec![
+            RolesUsers { id: 1, user_id: 1, role_id: 1 },
+            RolesUsers { id: 2, user_id: 2, role_id: 2 },
+        ];
+        let mut base = base;
+        base.data.roles = vec![Role::new(1, StaffRole::Administrator), Role::new(2, StaffRole::Editor)];
+        base.data.roles_users = vec![RolesUsers { id: 1, user_id: 1, role_id: 1 }];
+        base.data.products = vec![Product::new(1, "Gold", "gold".into())];
+        incoming.data.products = vec![Product::new(1, "Silver", "silver".into()), Product::new(2, "Gold", "gold".into())];
+        incoming.data.posts_products = vec![
+            PostsProducts { id: 1, post_id: 1, product_id: 1, sort_order: 0 },
+            PostsProducts { id: 2, post_id: 1, product_id: 2, sort_order: 1 },
+        ];
+
+        let (merged, report) = ImportMerger::merge(base, incoming, SlugConflict::Skip);
+
+        assert_eq!(report.added, 1);
+        assert_eq!(report.tags_reused, 1);
+        assert_eq!(report.users_reused, 1);
+        assert_eq!(merged.data.posts.len(), 3);
+        assert_eq!(merged.data.posts[2].id, 3);
+        assert_eq!(merged.data.users.len(), 2);
+        assert_eq!(merged.data.roles.len(), 3);
+        assert_eq!(merged.data.roles_users.len(), 2);
+        assert_eq!(merged.data.roles_users[1].user_id, 2);
+        assert_eq!(merged.data.roles_users[1].role_id, 2);
+        assert_eq!(merged.data.tags.len(), 3);
+        assert_eq!(merged.data.tags[2].id, 3);
+
+        let tags: Vec<_> = merged.data.posts_tags.iter().filter(|r| r.post_id == 3).collect();
+        assert_eq!(tags.iter().map(|r| r.tag_id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(tags[0].id, 3);
+        let authors: Vec<_> = merged.data.posts_authors.iter().filter(|r| r.post_id == 3).collect();
+        assert_eq!(authors[0].author_id, 1);
+        assert_eq!(merged.data.products.len(), 2);
+        let tiers: Vec<_> = merged.data.posts_products.iter().map(|r| (r.post_id, r.product_id)).collect();
+        assert_eq!(tiers, vec![(3, 2), (3, 1)]);
+    }
+
+    #[test]
+    fn test_merge_slug_conflicts() {
+        let base = || import(&["hello", "hello-2"], &["news"], &[("jane", "")]);
+        let incoming = || import(&["hello"], &["rust"], &[("bob", "")]);
+
+        let (merged, report) = ImportMerger::merge(base(), incoming(), SlugConflict::Skip);
+        assert_eq!(report.skipped, vec!["hello"]);
+        assert_eq!(merged.data.posts.len(), 2);
+        assert_eq!(merged.data.posts_tags.len(), 2);
+        assert_eq!(merged.data.tags.len(), 1);
+        assert_eq!(merged.data.users.len(), 1);
+
+        let with_tier = || {
+            let mut incoming = incoming();
+            incoming.data.products = vec![Product::new(1, "Gold", "gold".into())];
+            incoming.data.posts_products = vec![PostsProducts { id: 1, post_id: 1, product_id: 1, sort_order: 0 }];
+            incoming
+        };
+        let (merged, _) = ImportMerger::merge(base(), with_tier(), SlugConflict::Skip);
+        assert!(merged.data.products.is_empty());
+        assert!(merged.data.posts_products.is_empty());
+
+        let (merged, report) = ImportMerger::merge(base(), incoming(), SlugConflict::Rename);
+        assert_eq!(report.renamed, vec![("hello".to_string(), "hello-3".to_string())]);
+        assert_eq!(merged.data.posts[2].slug, "hello-3");
+
+        let incoming = || {
+            let mut incoming = incoming();
+            let og_title = Some("New".to_string());
+            incoming.data.posts_meta.push(PostsMeta { id: 1, post_id: 1, og_title, ..Default::default() });
+            incoming
+        };
+        let mut base = base();
+        base.data.posts_meta.push(PostsMeta { id: 1, post_id: 1, og_title: Some("Old".into()), ..Default::default() });
+        let (merged, report) = ImportMerger::merge(base, incoming(), SlugConflict::Overwrite);
+        assert_eq!(report.overwritten, vec!["hello"]);
+        assert_eq!(merged.data.posts.len(), 2);
+        let rows: Vec<_> = merged.data.posts_tags.iter().filter(|r| r.post_id == 1).collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(merged.data.posts_meta.len(), 1);
+        assert_eq!(merged.data.posts_meta[0].og_title.as_deref(), Some("New"));
+        assert_eq!(rows[0].tag_id, 2);
+        let authors: Vec<_> = merged.data.posts_authors.iter().filter(|r| r.post_id == 1).collect();
+        assert_eq!(authors[0].author_id, 2);
+    }
+
+    #[test]
+    fn test_default_author_goes_to_the_site_owner() {
+        let mut base = import(&["hello"], &["news"], &[("jane", "jane@x.org"), ("owner", "owner@x.org")]);
+        let owner = Role { name: "Owner".into(), ..Role::new(2, StaffRole::Administrator) };
+        base.data.roles = vec![Role::new(1, StaffRole::Editor), owner];
+        base.data.roles_users = vec![
+            RolesUsers { id: 1, user_id: 1, role_id: 1 },
+            RolesUsers { id: 2, user_id: 2, role_id: 2 },
+        ];
+        let mut incoming = import(&["fresh"], &["rust"], &[]);
+        incoming.data.users.push(User {
+            id: 1,
+            name: DEFAULT_AUTHOR.into(),
+            slug: "default-author".into(),
+            email: "default-author@example.com".into(),
+            ..Default::default()
+        });
+
+        let (merged, _) = ImportMerger::merge(base, incoming, SlugConflict::Skip);
+        assert_eq!(merged.data.users.len(), 2);
+        let authors: Vec<_> = merged.data.posts_authors.iter().filter(|r| r.post_id == 2).collect();
+        assert_eq!(authors[0].author_id, 2);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::models::ghost::MobiledocMode;
+use crate::processors::{LexicalSerializer, MarkdownProcessor};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const MOBILEDOC_VERSION: &str = "0.3.1";
+
+const MARKUP_SECTION: u64 = 1;
+const LIST_SECTION: u64 = 3;
+const CARD_SECTION: u64 = 10;
+const TEXT_MARKER: u64 = 0;
+const ATOM_MARKER: u64 = 1;
+
+/// Builds `mobiledoc` documents for Ghost 2.x-4.x.
+///
+/// [`MobiledocMode::MarkdownCard`] keeps the whole post in one Ghost markdown
+/// card so it can still be edited as Markdown, split in two around a paywall
+/// card when the post has one. [`MobiledocMode::Sections`]
+/// emits native sections, reusing the Lexical conversion for structure.
+/// Mobiledoc has no nested lists, so nested items are flattened into their
+/// parent list the way Ghost's own HTML converter does.
+pub struct MobiledocSerializer<'a> {
+    image_urls: &'a HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct Document {
+    atoms: Vec<Value>,
+    cards: Vec<Value>,
+    markups: Vec<Value>,
+    sections: Vec<Value>,
+}
+
+impl<'a> MobiledocSerializer<'a> {
+    /// `image_urls` maps image references as written in the Markdown to the
+    /// URLs they should carry in the document.
+    pub fn new(image_urls: &'a HashMap<String, String>) -> Self {
+        Self { image_urls }
+    }
+
+    pub fn to_mobiledoc(&self, markdown: &str, mode: MobiledocMode) -> Value {
+        match mode {
+            MobiledocMode::MarkdownCard => self.markdown_card(markdown),
+            MobiledocMode::Sections => self.sections(markdown),
+        }
+    }
+
+    pub fn to_json_string(&self, markdown: &str, mode: MobiledocMode) -> String {
+        self.to_mobiledoc(markdown, mode).to_string()
+    }
+
+    fn markdown_card(&self, markdown: &str) -> Value {
+        let markdown = MarkdownProcessor::rewrite_image_urls(markdown, self.image_urls);
+        let mut doc = Document::default();
+        // The paywall is a card of its own, so the Markdown is split around it
+        let mut start = 0;
+        for marker in MarkdownProcessor::paywall_markers(&markdown) {
+            let before = markdown[start..marker.start].trim_end();
+            if !before.is_empty() {
+                doc.push_card("markdown", json!({ "markdown": format!("{}\n", before) }));
+            }
+            doc.push_card("paywall", json!({}));
+            start = marker.end;
+        }
+        let rest = markdown[start..].trim_start_matches(['\r', '\n']);
+        if start == 0 || !rest.trim().is_empty() {
+            doc.push_card("markdown", json!({ "markdown": rest }));
+        }
+        doc.finish()
+    }
+
+    fn sections(&self, markdown: &str) -> Value {
+        let lexical = LexicalSerializer::new(self.image_urls).to_lexical(markdown);
+        let mut doc = Document::default();
+
+        for node in lexical["root"]["children"].as_array().into_iter().flatten() {
+            match node["type"].as_str().unwrap_or("") {
+                "paragraph" => doc.push_markup_section("p", node),
+                "extended-heading" => {
+                    doc.push_markup_section(node["tag"].as_str().unwrap_or("h2"), node)
+                }
+                "extended-quote" => doc.push_markup_section("blockquote", node),
+                "list" => doc.push_list_section(node),
+                "codeblock" => doc.push_card(
+                    "code",
+                    json!({ "code": node["code"], "language": node["language"] }),
+                ),
+                "image" => doc.push_card(
+                    "image",
+                    json!({
+                        "src": node["src"],
+                        "alt": node["alt"],
+                        "title": node["title"],
+                        "caption": node["caption"],
+                        "href": node["href"]
+                    }),
+                ),
+                "horizontalrule" => doc.push_card("hr", json!({})),
+                "paywall" => doc.push_card("paywall", json!({})),
+                _ => doc.push_card("html", json!({ "html": node["html"] })),
+            }
+        }
+
+        doc.finish()
+    }
+}
+
+impl Document {
+    fn finish(self) -> Value {
+        json!({
+            "version": MOBILEDOC_VERSION,
+            "atoms": self.atoms,
+            "cards": self.cards,
+            "markups": self.markups,
+            "sections": self.sections
+        })
+    }
+
+    fn push_card(&mut self, name: &str, payload: Value) {
+        self.cards.push(json!([name, payload]));
+        self.sections.push(json!([CARD_SECTION, self.cards.len() - 1]));
+    }
+
+    fn push_markup_section(&mut self, tag: &str, node: &Value) {
+        let markers = self.markers(&node["children"]);
+        self.sections.push(json!([MARKUP_SECTION, tag, markers]));
+    }
+
+    fn push_list_section(&mut self, node: &Value) {
+        let tag = node["tag"].as_str().unwrap_or("ul").to_string();
+        let mut items = Vec::new();
+        self.collect_list_items(node, &mut items);
+        self.sections.push(json!([LIST_SECTION, tag, items]));
+    }
+
+    fn collect_list_items(&mut self, list: &Value, items: &mut Vec<Value>) {
+        for item in list["children"].as_array().into_iter().flatten() {
+            let children = item["children"].as_array().cloned().unwrap_or_default();
+            let (nested, inline): (Vec<Value>, Vec<Value>) =
+                children.into_iter().partition(|child| child["type"] == "list");
+            if !inline.is_empty() {
+                items.push(Value::Array(self.markers(&Value::Array(inline))));
+            }
+            for list in nested {
+                self.collect_list_items(&list, items);
+            }
+        }
+    }
+
+    /// Turns Lexical inline nodes into mobiledoc markers, opening and closing
+    /// markups so that each run of text carries the formatting it had.
+    fn markers(&mut self, children: &Value) -> Vec<Value> {
+        let mut runs: Vec<(Vec<usize>, Option<String>)> = Vec::new();
+        self.collect_runs(children, &[], &mut runs);
+
+        let mut markers: Vec<Value> = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+        for (wanted, text) in runs {
+            let common = open
+                .iter()
+                .zip(&wanted)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if let Some(last) = markers.last_mut() {
+                last[2] = json!(open.len() - common);
+            }
+            let opened = wanted[common..].to_vec();
+            open = wanted;
+
+            match text {
+                Some(text) => markers.push(json!([TEXT_MARKER, opened, 0, text])),
+                None => {
+                    self.atoms.push(json!(["soft-return", "", {}]));
+                    markers.push(json!([ATOM_MARKER, opened, 0, self.atoms.len() - 1]));
+                }
+            }
+        }
+        if let Some(last) = markers.last_mut() {
+            last[2] = json!(open.len());
+        }
+        markers
+    }
+
+    /// Flattens inline nodes into runs of (active markups, text); `None` text
+    /// stands for a line break.
+    fn collect_runs(
+        &mut self,
+        children: &Value,
+        outer: &[usize],
+        runs: &mut Vec<(Vec<usize>, Option<String>)>,
+    ) {
+        for child in children.as_array().into_iter().flatten() {
+            match child["type"].as_str().unwrap_or("") {
+                "extended-text" => {
+                    let mut wanted = outer.to_vec();
+                    let format = child["format"].as_u64().unwrap_or(0);
+                    for (bit, tag) in [(1, "strong"), (2, "em"), (4, "s"), (16, "code")] {
+                        if format & bit != 0 {
+                            wanted.push(self.markup(json!([tag])));
+                        }
+                    }
+                    // A fixed order lets neighbouring runs share open markups.
+                    wanted.sort_unstable();
+                    wanted.dedup();
+                    let text = child["text"].as_str().unwrap_or("").to_string();
+                    runs.push((wanted, Some(text)));
+                }
+                "link" => {
+                    let mut wanted = outer.to_vec();
+                    wanted.push(self.markup(json!(["a", ["href", child["url"]]])));
+                    self.collect_runs(&child["children"], &wanted, runs);
+                }
+                "linebreak" => {
+                    let mut wanted = outer.to_vec();
+                    wanted.sort_unstable();
+                    runs.push((wanted, None));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn markup(&mut self, markup: Value) -> usize {
+        match self.markups.iter().position(|m| *m == markup) {
+            Some(index) => index,
+            None => {
+                self.markups.push(markup);
+                self.markups.len() - 1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_card_mode() {
+        let mut urls = HashMap::new();
+        urls.insert(
+            "img/a.png".to_string(),
+            "__GHOST_URL__/content/images/img/a.png".to_string(),
+        );
+        let doc = MobiledocSerializer::new(&urls)
+            .to_mobiledoc("# Hi\n\n![a](img/a.png)\n", MobiledocMode::MarkdownCard);
+
+        assert_eq!(doc["version"], "0.3.1");
+        assert_eq!(doc["cards"][0][0], "markdown");
+        assert_eq!(
+            doc["cards"][0][1]["markdown"],
+            "# Hi\n\n![a](__GHOST_URL__/content/images/img/a.png)\n"
+        );
+        assert_eq!(doc["sections"], json!([[10, 0]]));
+    }
+
+    #[test]
+    fn test_paywall_card() {
+        let urls = HashMap::new();
+        let serializer = MobiledocSerializer::new(&urls);
+        let markdown = "Free\n\n<!--members-only-->\n\nPaid\n";
+
+        let doc = serializer.to_mobiledoc(markdown, MobiledocMode::MarkdownCard);
+        assert_eq!(
+            doc["cards"],
+            json!([
+                ["markdown", { "markdown": "Free\n" }],
+                ["paywall", {}],
+                ["markdown", { "markdown": "Paid\n" }]
+            ])
+        );
+
+        let doc = serializer.to_mobiledoc(markdown, MobiledocMode::Sections);
+        assert_eq!(doc["cards"], json!([["paywall", {}]]));
+        assert_eq!(doc["sections"][1], json!([10, 0]));
+    }
+
+    #[test]
+    fn test_sections_mode() {
+        let urls = HashMap::new();
+        let doc = MobiledocSerializer::new(&urls).to_mobiledoc(
+            "## Title\n\nPlain **bold [link](https://x.org)** end\n\n- a\n  - b\n\n```js\nx\n```\n",
+            MobiledocMode::Sections,
+        );
+
+        assert_eq!(doc["sections"][0], json!([1, "h2", [[0, [], 0, "Title"]]]));
+        assert_eq!(doc["markups"], json!([["strong"], ["a", ["href", "https://x.org"]]]));
+        assert_eq!(
+            doc["sections"][1],
+            json!([1, "p", [
+                [0, [], 0, "Plain "],
+                [0, [0], 0, "bold "],
+                [0, [1], 2, "link"],
+                [0, [], 0, " end"]
+            ]])
+        );
+        assert_eq!(doc["sections"][2], json!([3, "ul", [[[0, [], 0, "a"]], [[0, [], 0, "b"]]]]));
+        assert_eq!(doc["cards"][0], json!(["code", { "code": "x\n", "language": "js" }]));
+        assert_eq!(doc["sections"][3], json!([10, 0]));
+    }
+}
@@ -1,5 +1,33 @@
 pub mod markdown;
 pub mod ghost_export;
+pub mod assets;
+pub mod lexical;
+pub mod mobiledoc;
+pub mod ghost_reader;
+pub mod markdown_writer;
+pub mod markdown_export;
+pub mod merge;
+pub mod validate;
+pub mod slug;
+pub mod registry;
+pub mod authors;
+pub mod tags;
+pub mod pages;
+pub mod excerpt;
 
 pub use markdown::*;
-pub use ghost_export::*;
\ No newline at end of file
+pub use ghost_export::*;
+pub use assets::*;
+pub use lexical::*;
+pub use mobiledoc::*;
+pub use ghost_reader::*;
+pub use markdown_writer::*;
+pub use markdown_export::*;
+pub use merge::*;
+pub use validate::*;
+pub use slug::*;
+pub use registry::*;
+pub use authors::*;
+pub use tags::*;
+pub use pages::*;
+pub use excerpt::*;
@@ -0,0 +1,124 @@
+use crate::models::ProcessedMarkdown;
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+
+/// `type` and `layout` values that mark a page unless configured otherwise.
+pub const DEFAULT_PAGE_LAYOUTS: [&str; 1] = ["page"];
+
+/// Directories that hold pages in Jekyll, Hugo and most other generators.
+pub const DEFAULT_PAGE_GLOBS: [&str; 3] = ["pages/**", "_pages/**", "content/pages/**"];
+
+/// Decides which Markdown files become Ghost pages instead of posts.
+///
+/// A file is a page when its frontmatter `type` or `layout` is one of the
+/// page layouts, when its path under the input directory matches one of the
+/// globs, or when it is a Hugo `_index.md` or an `index.md` bundle under `content`.
+#[derive(Debug, Clone)]
+pub struct PageRules {
+    root: PathBuf,
+    layouts: Vec<String>,
+    globs: GlobSet,
+}
+
+impl PageRules {
+    pub fn new<L, G>(root: &Path, layouts: L, globs: G) -> Result<Self>
+    where
+        L: IntoIterator,
+        L::Item: AsRef<str>,
+        G: IntoIterator,
+        G::Item: AsRef<str>,
+    {
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            let glob = glob.as_ref();
+            builder.add(Glob::new(glob).with_context(|| format!("Invalid page glob {:?}", glob))?);
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            layouts: layouts.into_iter().map(|l| l.as_ref().to_lowercase()).collect(),
+            globs: builder.build()?,
+        })
+    }
+
+    pub fn is_page(&self, processed: &ProcessedMarkdown) -> bool {
+        let frontmatter = &processed.frontmatter;
+        let kind = frontmatter.extra.get("type").and_then(|v| v.as_str());
+        let by_layout = [kind, frontmatter.layout.as_deref()]
+            .into_iter()
+            .flatten()
+            .any(|layout| self.layouts.contains(&layout.trim().to_lowercase()));
+
+        let path = Path::new(&processed.file_path);
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        by_layout || self.globs.is_match(relative) || Self::is_hugo_page(path)
+    }
+
+    /// Hugo's `_index.md` section pages, and `index.md` bundles directly
+    /// inside a `content` directory such as `content/about/index.md`. Other
+    /// bundles like `content/posts/hello/index.md` are posts, even when the
+    /// input directory is `content/posts`.
+    fn is_hugo_page(path: &Path) -> bool {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+        if name == "_index.md" {
+            return true;
+        }
+        let Some(bundle_parent) = path.parent().and_then(Path::parent) else {
+            return false;
+        };
+        // `./about/index.md` has no directory name to check until resolved
+        let bundle_parent = bundle_parent.canonicalize().unwrap_or_else(|_| bundle_parent.to_path_buf());
+        name == "index.md" && bundle_parent.file_name().is_some_and(|n| n == "content")
+    }
+}
+
+impl Default for PageRules {
+    fn default() -> Self {
+        Self::new(Path::new(""), DEFAULT_PAGE_LAYOUTS, DEFAULT_PAGE_GLOBS)
+            .expect("default page globs are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Frontmatter;
+
+    fn file(path: &str, yaml: &str) -> ProcessedMarkdown {
+        ProcessedMarkdown {
+            frontmatter: serde_yaml::from_str::<Option<Frontmatter>>(yaml).unwrap().unwrap_or_default(),
+            file_path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_page_rules() {
+        let rules = PageRules::new(Path::new("site"), ["page", "Static"], DEFAULT_PAGE_GLOBS).unwrap();
+
+        assert!(rules.is_page(&file("site/about.md", "layout: page")));
+        assert!(rules.is_page(&file("site/uses.md", "type: static")));
+        assert!(!rules.is_page(&file("site/hello.md", "layout: post")));
+
+        assert!(rules.is_page(&file("site/content/pages/contact.md", "")));
+        assert!(rules.is_page(&file("site/_pages/now.md", "")));
+        assert!(!rules.is_page(&file("site/posts/pages.md", "")));
+
+        assert!(rules.is_page(&file("site/content/posts/_index.md", "")));
+        assert!(rules.is_page(&file("site/content/about/index.md", "")));
+        assert!(!rules.is_page(&file("site/about/index.md", "")));
+        assert!(!rules.is_page(&file("site/content/posts/hello/index.md", "")));
+    }
+
+    #[test]
+    fn test_bundles_below_the_input_root() {
+        let rules = PageRules::new(Path::new("site/content/posts"), DEFAULT_PAGE_LAYOUTS, DEFAULT_PAGE_GLOBS).unwrap();
+        assert!(!rules.is_page(&file("site/content/posts/hello/index.md", "")));
+        assert!(rules.is_page(&file("site/content/posts/_index.md", "")));
+
+        let rules = PageRules::new(Path::new("site/content"), DEFAULT_PAGE_LAYOUTS, DEFAULT_PAGE_GLOBS).unwrap();
+        assert!(rules.is_page(&file("site/content/about/index.md", "")));
+    }
+}
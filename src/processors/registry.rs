@@ -0,0 +1,175 @@
+use crate::models::ProcessedMarkdown;
+use crate::processors::{AssetResolver, MarkdownProcessor, ResolvedAsset};
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// One entry of a [`ProfileRegistry`], such as an author or a tag.
+pub trait Profile: DeserializeOwned + Default {
+    /// What the profiles describe, for error messages
+    const KIND: &'static str;
+    /// Registry files and directories looked for in the input directory
+    const DISCOVERED: &'static [&'static str];
+
+    /// File the profile was read from; images are relative to it
+    fn source(&self) -> &Path;
+    fn set_source(&mut self, source: PathBuf);
+    /// Names, slug and other spellings posts may use besides the key
+    fn names(&self) -> Vec<&String>;
+    /// Fields that may point at local images
+    fn images(&mut self) -> Vec<&mut Option<String>>;
+    /// `(key, file)` for every profile page in a registry directory
+    fn pages(dir: &Path) -> Result<Vec<(String, PathBuf)>>;
+    /// Fills what the page's frontmatter fields left out from its title,
+    /// slug, image and body.
+    fn complete(&mut self, page: ProcessedMarkdown);
+    /// Checks and normalises the profile stored under `key`.
+    fn normalize(&mut self, _key: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Profiles keyed by the name posts use for them.
+///
+/// Read from a YAML file mapping keys to profiles, or from a directory of
+/// Markdown pages whose frontmatter holds the profile.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileRegistry<P> {
+    profiles: BTreeMap<String, P>,
+    /// Lowercased key, name, slug or other spelling -> profile key
+    lookup: HashMap<String, String>,
+}
+
+impl<P: Profile> ProfileRegistry<P> {
+    pub fn load(path: &Path) -> Result<Self> {
+        let profiles = if path.is_dir() {
+            Self::read_dir(path)?
+        } else {
+            Self::read_yaml(path)?
+        };
+
+        let mut lookup = HashMap::new();
+        let mut registry = BTreeMap::new();
+        for (key, mut profile) in profiles {
+            profile.normalize(&key)?;
+            for name in std::iter::once(&key).chain(profile.names()) {
+                lookup
+                    .entry(name.trim().to_lowercase())
+                    .or_insert_with(|| key.clone());
+            }
+            registry.insert(key, profile);
+        }
+        Ok(Self { profiles: registry, lookup })
+    }
+
+    /// Loads the first registry found next to the posts, if any.
+    pub fn discover(input: &Path) -> Result<Option<Self>> {
+        P::DISCOVERED
+            .iter()
+            .map(|candidate| input.join(candidate))
+            .find(|path| path.is_file() || Self::has_pages(path))
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    /// Whether `path` is one of the files the registry was read from.
+    pub fn is_source(&self, path: &Path) -> bool {
+        self.profiles.values().any(|profile| profile.source() == path)
+    }
+
+    /// Key of the profile `name` refers to, by key, name, slug or any other
+    /// spelling the profile lists.
+    pub fn key_for(&self, name: &str) -> Option<&str> {
+        self.lookup.get(&name.trim().to_lowercase()).map(String::as_str)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&P> {
+        self.key_for(name).and_then(|key| self.profiles.get(key))
+    }
+
+    /// Points local images at their `content/images` location and returns
+    /// the files to bundle, per profile key.
+    pub fn rewrite_images(&mut self, resolver: &AssetResolver) -> Vec<(String, Vec<ResolvedAsset>)> {
+        let mut bundled = Vec::new();
+        for (key, profile) in &mut self.profiles {
+            let source = profile.source().to_path_buf();
+            let mut assets = Vec::new();
+            for image in profile.images().into_iter().flat_map(Option::as_mut) {
+                if let Some(asset) = resolver.resolve(image, &source) {
+                    *image = asset.url.clone();
+                    assets.push(asset);
+                }
+            }
+            if !assets.is_empty() {
+                bundled.push((key.clone(), assets));
+            }
+        }
+        bundled
+    }
+
+    fn read_yaml(path: &Path) -> Result<BTreeMap<String, P>> {
+        let yaml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {} registry {:?}", P::KIND, path))?;
+        let mut profiles: BTreeMap<String, P> = serde_yaml::from_str(&yaml)
+            .with_context(|| format!("Failed to parse {} registry {:?}", P::KIND, path))?;
+        for profile in profiles.values_mut() {
+            profile.set_source(path.to_path_buf());
+        }
+        Ok(profiles)
+    }
+
+    fn read_dir(dir: &Path) -> Result<BTreeMap<String, P>> {
+        let mut profiles = BTreeMap::new();
+        for (key, path) in P::pages(dir)? {
+            let mut page = MarkdownProcessor::process_file(&path)?;
+            let fields = std::mem::take(&mut page.frontmatter.extra)
+                .into_iter()
+                .map(|(k, v)| (serde_yaml::Value::String(k), v))
+                .collect();
+            let mut profile: P = serde_yaml::from_value(serde_yaml::Value::Mapping(fields))
+                .with_context(|| format!("Failed to parse {} profile {:?}", P::KIND, path))?;
+            profile.complete(page);
+            profile.set_source(path);
+            profiles.insert(key, profile);
+        }
+        Ok(profiles)
+    }
+
+    fn has_pages(dir: &Path) -> bool {
+        dir.is_dir() && P::pages(dir).is_ok_and(|pages| !pages.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::TagProfile;
+
+    #[test]
+    fn test_discover_prefers_the_yaml_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let term = dir.path().join("tags/go");
+        std::fs::create_dir_all(&term).unwrap();
+        std::fs::write(term.join("_index.md"), "---\ntitle: Go\n---\n").unwrap();
+        std::fs::write(
+            dir.path().join("tags.yaml"),
+            "golang:\n  name: Go\n  image: https://example.org/go.png\n",
+        )
+        .unwrap();
+
+        let mut registry = ProfileRegistry::<TagProfile>::discover(dir.path()).unwrap().unwrap();
+        assert_eq!(registry.key_for(" GOLANG "), Some("golang"));
+        assert_eq!(registry.key_for("go"), Some("golang"));
+        assert!(registry.is_source(&dir.path().join("tags.yaml")));
+        assert!(!registry.is_source(&term.join("_index.md")));
+        assert!(registry.rewrite_images(&AssetResolver::new(dir.path())).is_empty());
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(ProfileRegistry::<TagProfile>::discover(empty.path()).unwrap().is_none());
+    }
+}
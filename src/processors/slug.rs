@@ -0,0 +1,334 @@
+use deunicode::deunicode_char;
+use std::collections::{HashMap, HashSet};
+
+/// Longest slug Ghost stores, in characters.
+pub const MAX_SLUG_LENGTH: usize = 191;
+
+/// Apostrophes are dropped rather than split on, so `Test's` stays one word.
+const APOSTROPHES: [char; 3] = ['\'', '\u{2018}', '\u{2019}'];
+
+/// How characters outside ASCII end up in generated slugs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SlugStyle {
+    /// Keep letters and digits from every script, e.g. `café-日記`
+    #[default]
+    Unicode,
+    /// Strip accents from Latin letters and drop other scripts, e.g. `cafe`
+    Ascii,
+    /// Spell accented Latin, Cyrillic, Greek and other alphabets in ASCII,
+    /// leaving Chinese and Japanese characters as they are
+    Transliterate,
+    /// Like `transliterate`, and read Chinese characters as Pinyin, e.g.
+    /// `chu-shi-pei-zhi`
+    Pinyin,
+    /// Like `transliterate`, and spell Japanese kana in Hepburn romaji, e.g.
+    /// `kyaripamyupamyu`. Kanji are left as they are.
+    Romaji,
+}
+
+impl SlugStyle {
+    /// Lowercases `text` and joins its words with `-`, cut to
+    /// [`MAX_SLUG_LENGTH`]. May return an empty string.
+    pub fn slugify(self, text: &str) -> String {
+        let mut slug = String::new();
+        let mut separate = false;
+        for c in self.spell(text).chars() {
+            if c.is_alphanumeric() {
+                if separate && !slug.is_empty() {
+                    slug.push('-');
+                }
+                separate = false;
+                slug.extend(c.to_lowercase());
+            } else {
+                separate = true;
+            }
+        }
+        truncate_slug(&mut slug, MAX_SLUG_LENGTH);
+        slug
+    }
+
+    /// Rewrites the characters this style does not keep, with spaces where
+    /// nothing is left.
+    fn spell(self, text: &str) -> String {
+        let mut spelled = String::new();
+        let mut kana = String::new();
+        for c in text.chars().filter(|c| !APOSTROPHES.contains(c)) {
+            if self == SlugStyle::Romaji && is_kana(c) {
+                kana.push(c);
+                continue;
+            }
+            if !kana.is_empty() {
+                spelled.push_str(&romanize_kana(&std::mem::take(&mut kana)));
+                spelled.push(' ');
+            }
+            match self {
+                _ if c.is_ascii() => spelled.push(c),
+                SlugStyle::Unicode => spelled.push(c),
+                SlugStyle::Ascii if is_latin(c) => spelled.push_str(deunicode_char(c).unwrap_or(" ")),
+                SlugStyle::Ascii => spelled.push(' '),
+                SlugStyle::Pinyin if is_han(c) => {
+                    // Han syllables come back capitalized with a trailing space
+                    spelled.push(' ');
+                    spelled.push_str(deunicode_char(c).unwrap_or(" "));
+                }
+                _ if is_han(c) || is_kana(c) => spelled.push(c),
+                _ => spelled.push_str(deunicode_char(c).unwrap_or(" ")),
+            }
+        }
+        if !kana.is_empty() {
+            spelled.push(' ');
+            spelled.push_str(&romanize_kana(&kana));
+        }
+        spelled
+    }
+}
+
+fn is_latin(c: char) -> bool {
+    matches!(c, '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}')
+}
+
+pub(crate) fn is_han(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+pub(crate) fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}' | '\u{30FC}')
+}
+
+/// Spells a run of hiragana and katakana in Hepburn romaji, without macrons:
+/// `っ` doubles the next consonant, small `ゃゅょ` and vowels merge into the
+/// syllable before them and `ー` is dropped.
+fn romanize_kana(kana: &str) -> String {
+    let mut romaji = String::new();
+    let mut last = "";
+    let mut double_next = false;
+    for c in kana.chars() {
+        // Katakana sit 0x60 above the matching hiragana
+        let c = match c {
+            '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        };
+        match c {
+            'っ' => double_next = true,
+            'ゃ' | 'ゅ' | 'ょ' | 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' => {
+                let small = hepburn(c).unwrap_or_default();
+                let merges = last.len() > 1 && last.ends_with(['a', 'i', 'u', 'e', 'o']);
+                if merges {
+                    romaji.pop();
+                    // しゃ is "sha" and きゃ "kya", but ファ is just "fa"
+                    let palatal = last.ends_with('i') && small.starts_with('y');
+                    let small = if palatal && (last.ends_with("hi") || last == "ji") {
+                        &small[1..]
+                    } else if palatal {
+                        small
+                    } else {
+                        small.trim_start_matches('y')
+                    };
+                    romaji.push_str(small);
+                } else {
+                    romaji.push_str(small);
+                }
+                last = "";
+            }
+            _ => {
+                let Some(syllable) = hepburn(c) else {
+                    last = "";
+                    continue;
+                };
+                if std::mem::take(&mut double_next) {
+                    match syllable.chars().next() {
+                        Some('c') => romaji.push('t'),
+                        Some(first) if !"aiueon".contains(first) => romaji.push(first),
+                        _ => {}
+                    }
+                }
+                romaji.push_str(syllable);
+                last = syllable;
+            }
+        }
+    }
+    romaji
+}
+
+fn hepburn(c: char) -> Option<&'static str> {
+    Some(match c {
+        'あ' | 'ぁ' => "a", 'い' | 'ぃ' => "i", 'う' | 'ぅ' => "u", 'え' | 'ぇ' => "e", 'お' | 'ぉ' => "o",
+        'か' | 'ゕ' => "ka", 'き' => "ki", 'く' => "ku", 'け' | 'ゖ' => "ke", 'こ' => "ko",
+        'が' => "ga", 'ぎ' => "gi", 'ぐ' => "gu", 'げ' => "ge", 'ご' => "go",
+        'さ' => "sa", 'し' => "shi", 'す' => "su", 'せ' => "se", 'そ' => "so",
+        'ざ' => "za", 'じ' => "ji", 'ず' => "zu", 'ぜ' => "ze", 'ぞ' => "zo",
+        'た' => "ta", 'ち' => "chi", 'つ' => "tsu", 'て' => "te", 'と' => "to",
+        'だ' => "da", 'ぢ' => "ji", 'づ' => "zu", 'で' => "de", 'ど' => "do",
+        'な' => "na", 'に' => "ni", 'ぬ' => "nu", 'ね' => "ne", 'の' => "no",
+        'は' => "ha", 'ひ' => "hi", 'ふ' => "fu", 'へ' => "he", 'ほ' => "ho",
+        'ば' => "ba", 'び' => "bi", 'ぶ' => "bu", 'べ' => "be", 'ぼ' => "bo",
+        'ぱ' => "pa", 'ぴ' => "pi", 'ぷ' => "pu", 'ぺ' => "pe", 'ぽ' => "po",
+        'ま' => "ma", 'み' => "mi", 'む' => "mu", 'め' => "me", 'も' => "mo",
+        'や' | 'ゃ' => "ya", 'ゆ' | 'ゅ' => "yu", 'よ' | 'ょ' => "yo",
+        'ら' => "ra", 'り' => "ri", 'る' => "ru", 'れ' => "re", 'ろ' => "ro",
+        'わ' | 'ゎ' => "wa", 'ゐ' => "i", 'ゑ' => "e", 'を' => "o", 'ん' => "n",
+        'ゔ' => "vu", 'ヷ' => "va", 'ヸ' => "vi", 'ヹ' => "ve", 'ヺ' => "vo",
+        _ => return None,
+    })
+}
+
+/// Cuts `slug` to `limit` characters without leaving a trailing `-`.
+pub fn truncate_slug(slug: &mut String, limit: usize) {
+    if let Some((index, _)) = slug.char_indices().nth(limit) {
+        slug.truncate(index);
+    }
+    let trimmed = slug.trim_end_matches(['-', ' ']).len();
+    slug.truncate(trimmed);
+}
+
+/// First of `slug-2`, `slug-3`, ... not in `taken`, shortened so the suffix
+/// still fits within [`MAX_SLUG_LENGTH`].
+pub fn next_free_slug(slug: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| {
+            let suffix = format!("-{}", n);
+            let mut base = slug.to_string();
+            truncate_slug(&mut base, MAX_SLUG_LENGTH - suffix.len());
+            base + &suffix
+        })
+        .find(|candidate| !taken.contains(candidate))
+        .expect("slug counter exhausted")
+}
+
+/// Tables whose slugs must be unique on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlugKind {
+    /// Posts and pages, which share one namespace
+    Post,
+    Tag,
+    User,
+    Tier,
+}
+
+impl SlugKind {
+    /// Slug for names that leave nothing behind once slugified.
+    fn fallback(self) -> &'static str {
+        match self {
+            SlugKind::Post => "untitled",
+            SlugKind::Tag => "tag",
+            SlugKind::User => "author",
+            SlugKind::Tier => "tier",
+        }
+    }
+}
+
+/// Hands out slugs that are unique per [`SlugKind`].
+///
+/// A slug that is already taken gets the next free `-2`, `-3`, ... suffix,
+/// so the result only depends on the order slugs are claimed in.
+#[derive(Debug, Default)]
+pub struct SlugRegistry {
+    style: SlugStyle,
+    taken: HashMap<SlugKind, HashSet<String>>,
+}
+
+impl SlugRegistry {
+    pub fn new(style: SlugStyle) -> Self {
+        Self {
+            style,
+            taken: HashMap::new(),
+        }
+    }
+
+    /// Slugifies `text` and reserves the first free variant of it.
+    pub fn claim(&mut self, kind: SlugKind, text: &str) -> String {
+        let slug = self.style.slugify(text);
+        self.take(kind, slug)
+    }
+
+    /// Reserves a slug that was given rather than generated, such as one set
+    /// in frontmatter, or the first free variant of it. It is kept as
+    /// written and only shortened when it is too long for Ghost.
+    pub fn reserve(&mut self, kind: SlugKind, slug: &str) -> String {
+        let mut slug = slug.trim().to_string();
+        if slug.chars().count() > MAX_SLUG_LENGTH {
+            truncate_slug(&mut slug, MAX_SLUG_LENGTH);
+        }
+        self.take(kind, slug)
+    }
+
+    fn take(&mut self, kind: SlugKind, mut slug: String) -> String {
+        if slug.is_empty() {
+            slug = kind.fallback().to_string();
+        }
+        let taken = self.taken.entry(kind).or_default();
+        if taken.contains(&slug) {
+            slug = next_free_slug(&slug, taken);
+        }
+        taken.insert(slug.clone());
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_styles() {
+        let unicode = SlugStyle::Unicode;
+        assert_eq!(unicode.slugify("Hello World"), "hello-world");
+        assert_eq!(unicode.slugify("Test's Post!"), "tests-post");
+        assert_eq!(unicode.slugify("  Multiple   Spaces  "), "multiple-spaces");
+        assert_eq!(unicode.slugify("Café au lait"), "café-au-lait");
+        assert_eq!(unicode.slugify("webpack3.x初始配置"), "webpack3-x初始配置");
+
+        assert_eq!(SlugStyle::Ascii.slugify("Café Straße"), "cafe-strasse");
+        assert_eq!(SlugStyle::Ascii.slugify("Rust 日記"), "rust");
+        assert_eq!(SlugStyle::Transliterate.slugify("Café 日記"), "cafe-日記");
+        assert_eq!(SlugStyle::Transliterate.slugify("Привет, мир"), "privet-mir");
+        assert_eq!(SlugStyle::Transliterate.slugify("Ελληνικά"), "ellenika");
+
+        assert_eq!(unicode.slugify(&"é".repeat(300)).chars().count(), MAX_SLUG_LENGTH);
+    }
+
+    #[test]
+    fn test_cjk_slug_styles() {
+        let pinyin = SlugStyle::Pinyin;
+        assert_eq!(pinyin.slugify("webpack3.x初始配置"), "webpack3-x-chu-shi-pei-zhi");
+        assert_eq!(pinyin.slugify("Vue 组件通信"), "vue-zu-jian-tong-xin");
+        assert_eq!(pinyin.slugify("Москва 北京"), "moskva-bei-jing");
+
+        let romaji = SlugStyle::Romaji;
+        assert_eq!(romaji.slugify("きゃりーぱみゅぱみゅ"), "kyaripamyupamyu");
+        assert_eq!(romaji.slugify("コーヒーとチョコレート"), "kohitochokoreto");
+        assert_eq!(romaji.slugify("ヴァイオリン"), "vaiorin");
+        assert_eq!(romaji.slugify("ちょっと待って"), "chotto-待-tte");
+        assert_eq!(romaji.slugify("マッチ・シャッフル"), "matchi-shaffuru");
+        assert_eq!(romaji.slugify("ティーパーティー"), "tipati");
+    }
+
+    #[test]
+    fn test_registry_resolves_collisions() {
+        let mut slugs = SlugRegistry::new(SlugStyle::Unicode);
+        assert_eq!(slugs.claim(SlugKind::Post, "Notes"), "notes");
+        assert_eq!(slugs.claim(SlugKind::Post, "notes"), "notes-2");
+        assert_eq!(slugs.claim(SlugKind::Post, "Notes!"), "notes-3");
+        assert_eq!(slugs.claim(SlugKind::Tag, "Notes"), "notes");
+        assert_eq!(slugs.claim(SlugKind::Post, "!!!"), "untitled");
+
+        let long = "b".repeat(MAX_SLUG_LENGTH);
+        assert_eq!(slugs.claim(SlugKind::User, &long), long);
+        let second = slugs.claim(SlugKind::User, &long);
+        assert_eq!(second.len(), MAX_SLUG_LENGTH);
+        assert!(second.ends_with("b-2"));
+    }
+
+    #[test]
+    fn test_reserve_keeps_given_slugs() {
+        let mut slugs = SlugRegistry::new(SlugStyle::Ascii);
+        assert_eq!(slugs.reserve(SlugKind::Post, "my_old_url"), "my_old_url");
+        assert_eq!(slugs.reserve(SlugKind::Post, "日記"), "日記");
+        assert_eq!(slugs.reserve(SlugKind::Post, "my_old_url"), "my_old_url-2");
+        assert_eq!(slugs.claim(SlugKind::Post, "My old URL"), "my-old-url");
+        assert_eq!(slugs.reserve(SlugKind::Tag, &"x".repeat(300)).len(), MAX_SLUG_LENGTH);
+    }
+}
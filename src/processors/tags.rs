@@ -0,0 +1,189 @@
+use crate::models::ghost::Tag;
+use crate::models::ProcessedMarkdown;
+use crate::processors::{Profile, ProfileRegistry};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Registry files looked for in the input directory when `--tags` is not
+/// given. `../tags` matches Hugo, whose posts usually live in
+/// `content/posts` next to the `content/tags` term pages.
+const DISCOVERED: [&str; 5] = ["tags.yaml", "tags.yml", "tags", "content/tags", "../tags"];
+
+/// Index page Hugo and Zola use for a taxonomy term's own page.
+const TERM_PAGE: &str = "_index.md";
+
+/// Metadata for one tag, as written in the registry.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TagProfile {
+    #[serde(alias = "title")]
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+    #[serde(alias = "image")]
+    pub feature_image: Option<String>,
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    /// Hex colour such as `#ff6600`
+    #[serde(alias = "color", alias = "colour")]
+    pub accent_color: Option<String>,
+    /// Other spellings posts use for this tag
+    #[serde(default, alias = "aliases")]
+    pub synonyms: Vec<String>,
+    #[serde(skip)]
+    pub source: PathBuf,
+}
+
+impl Profile for TagProfile {
+    const KIND: &'static str = "tag";
+    const DISCOVERED: &'static [&'static str] = &DISCOVERED;
+
+    fn source(&self) -> &Path {
+        &self.source
+    }
+
+    fn set_source(&mut self, source: PathBuf) {
+        self.source = source;
+    }
+
+    fn names(&self) -> Vec<&String> {
+        [self.name.as_ref(), self.slug.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(&self.synonyms)
+            .collect()
+    }
+
+    fn images(&mut self) -> Vec<&mut Option<String>> {
+        vec![&mut self.feature_image]
+    }
+
+    /// `(term, <dir>/<term>/_index.md)` for every term directory with a page.
+    fn pages(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let mut pages = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path().join(TERM_PAGE);
+            if path.is_file() {
+                let term = path.parent().and_then(Path::file_name).unwrap_or_default();
+                pages.push((term.to_string_lossy().to_string(), path));
+            }
+        }
+        pages.sort();
+        Ok(pages)
+    }
+
+    fn complete(&mut self, page: ProcessedMarkdown) {
+        let frontmatter = page.frontmatter;
+        self.name = self.name.take().or(frontmatter.title);
+        self.slug = self.slug.take().or(frontmatter.slug);
+        self.feature_image = self.feature_image.take().or(frontmatter.image);
+        self.description = self.description.take().or(frontmatter.description);
+
+        let body = page.content.trim();
+        if self.description.is_none() && !body.is_empty() {
+            self.description = Some(body.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    /// Ghost only accepts six-digit hex colours.
+    fn normalize(&mut self, key: &str) -> Result<()> {
+        if let Some(color) = self.accent_color.take() {
+            let color = accent_color(&color)
+                .with_context(|| format!("Tag {:?} has an invalid colour {:?}", key, color))?;
+            self.accent_color = Some(color);
+        }
+        Ok(())
+    }
+}
+
+/// Tag metadata keyed by the name posts use for the tag.
+///
+/// Read from a `tags.yaml` mapping names to metadata, or from a Hugo or Zola
+/// taxonomy directory where `tags/<term>/_index.md` holds the metadata in its
+/// frontmatter and the description in its body.
+pub type TagRegistry = ProfileRegistry<TagProfile>;
+
+impl TagRegistry {
+    /// Fills the metadata of `tag` from the profile `name` refers to.
+    pub fn apply(&self, name: &str, tag: &mut Tag) {
+        let Some(profile) = self.get(name) else {
+            return;
+        };
+        if let Some(display_name) = &profile.name {
+            tag.name = display_name.clone();
+        }
+        tag.description = profile.description.clone();
+        tag.feature_image = profile.feature_image.clone();
+        tag.meta_title = profile.meta_title.clone();
+        tag.meta_description = profile.meta_description.clone();
+        tag.accent_color = profile.accent_color.clone();
+    }
+}
+
+/// Normalises `#FF6600` or `ff6600` to the lowercase `#ff6600` Ghost stores.
+fn accent_color(value: &str) -> Result<String> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("expected a hex colour such as #ff6600");
+    }
+    Ok(format!("#{}", hex.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::AssetResolver;
+
+    #[test]
+    fn test_tag_colours_and_synonyms() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("tags.yaml"),
+            r##"
+vue:
+  name: Vue.js
+  description: The progressive framework
+  image: images/vue.png
+  color: "#42B883"
+  synonyms: [VUE, vuejs]
+"##,
+        )
+        .unwrap();
+
+        let registry = TagRegistry::discover(dir.path()).unwrap().unwrap();
+        for name in ["VUE", "vue", "Vue.js", "vuejs"] {
+            assert_eq!(registry.key_for(name), Some("vue"));
+        }
+
+        let mut tag = Tag { name: "VUE".into(), ..Default::default() };
+        registry.apply("VUE", &mut tag);
+        assert_eq!(tag.name, "Vue.js");
+        assert_eq!(tag.accent_color.as_deref(), Some("#42b883"));
+        assert_eq!(tag.description.as_deref(), Some("The progressive framework"));
+
+        std::fs::write(dir.path().join("tags.yaml"), "vue:\n  color: green\n").unwrap();
+        assert!(TagRegistry::discover(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_hugo_term_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let term = dir.path().join("content/tags/rust");
+        std::fs::create_dir_all(&term).unwrap();
+        std::fs::write(term.join("crab.png"), b"png").unwrap();
+        std::fs::write(
+            term.join("_index.md"),
+            "---\ntitle: Rust\nimage: crab.png\nmeta_title: All about Rust\n---\n\nPosts about\nthe Rust language.\n",
+        )
+        .unwrap();
+
+        let mut registry = TagRegistry::discover(dir.path()).unwrap().unwrap();
+        let profile = registry.get("RUST").unwrap();
+        assert_eq!(profile.description.as_deref(), Some("Posts about the Rust language."));
+        assert_eq!(profile.meta_title.as_deref(), Some("All about Rust"));
+        assert!(registry.is_source(&term.join("_index.md")));
+
+        let bundled = registry.rewrite_images(&AssetResolver::new(dir.path()));
+        assert_eq!(bundled[0].1[0].zip_path, "content/images/content/tags/rust/crab.png");
+    }
+}
@@ -0,0 +1,541 @@
+use crate::models::ghost::{GhostImport, Post, PostStatus, PostVisibility};
+use crate::processors::{next_free_slug, truncate_slug, MAX_SLUG_LENGTH};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+const POST_LIMITS: [(&str, usize); 5] = [
+    ("title", 255),
+    ("slug", MAX_SLUG_LENGTH),
+    ("custom_excerpt", 300),
+    ("meta_title", 300),
+    ("meta_description", 500),
+];
+const TAG_LIMITS: [(&str, usize); 5] = [
+    ("name", 191),
+    ("slug", MAX_SLUG_LENGTH),
+    ("description", 500),
+    ("meta_title", 300),
+    ("meta_description", 500),
+];
+const POSTS_META_LIMITS: [(&str, usize); 8] = [
+    ("meta_title", 300),
+    ("meta_description", 500),
+    ("og_title", 300),
+    ("og_description", 500),
+    ("twitter_title", 300),
+    ("twitter_description", 500),
+    ("email_subject", 300),
+    ("feature_image_alt", 191),
+];
+const USER_LIMITS: [(&str, usize); 4] = [("name", 191), ("slug", MAX_SLUG_LENGTH), ("bio", 200), ("location", 150)];
+const EMAIL_LIMIT: usize = 191;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Ghost rejects the whole import
+    Error,
+    /// Ghost imports it, but probably not the way it was meant
+    Warning,
+}
+
+/// A single problem found in an import.
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    /// The record, e.g. `post "hello"` or `posts_tags row 3`
+    pub subject: String,
+    /// Markdown file the record was built from, when known
+    pub source: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", level, self.location(), self.message)
+    }
+}
+
+impl Issue {
+    /// The record, followed by its source file when known.
+    pub fn location(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{} ({})", self.subject, source),
+            None => self.subject.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Problems still present in the import
+    pub issues: Vec<Issue>,
+    /// Problems that were fixed in place
+    pub fixed: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn errors(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == Severity::Error).count()
+    }
+}
+
+/// Checks an import against Ghost's column limits and relation integrity.
+///
+/// With `fix` set, overlong fields are truncated, duplicate slugs get a
+/// numeric suffix and relation rows pointing nowhere are dropped; what is
+/// left over has to be fixed in the sources.
+pub struct Validator {
+    fix: bool,
+    report: ValidationReport,
+}
+
+impl Validator {
+    pub fn new(fix: bool) -> Self {
+        Self {
+            fix,
+            report: ValidationReport::default(),
+        }
+    }
+
+    pub fn run(mut self, import: &mut GhostImport) -> ValidationReport {
+        self.check_relations(import);
+        self.check_lengths(import);
+        self.check_slugs(import);
+        self.check_statuses(import);
+        self.check_visibility(import);
+        self.check_emails(import);
+        self.check_roles(import);
+        self.report
+    }
+
+    fn record(&mut self, fixed: bool, severity: Severity, subject: String, source: Option<&str>, message: String) {
+        let issue = Issue {
+            severity,
+            subject,
+            source: source.map(str::to_string),
+            message,
+        };
+        if fixed {
+            self.report.fixed.push(issue);
+        } else {
+            self.report.issues.push(issue);
+        }
+    }
+
+    fn check_relations(&mut self, import: &mut GhostImport) {
+        let data = &mut import.data;
+        let posts: HashMap<i32, Option<String>> = data
+            .posts
+            .iter()
+            .map(|p| (p.id, p.source_path.clone()))
+            .collect();
+        let tags: HashSet<i32> = data.tags.iter().map(|t| t.id).collect();
+        let users: HashSet<i32> = data.users.iter().map(|u| u.id).collect();
+        let products: HashSet<i32> = data.products.iter().map(|p| p.id).collect();
+        let fix = self.fix;
+
+        let mut dangling = Vec::new();
+        data.posts_tags.retain(|row| {
+            let missing = match (posts.contains_key(&row.post_id), tags.contains(&row.tag_id)) {
+                (false, _) => format!("post_id {} does not exist", row.post_id),
+                (_, false) => format!("tag_id {} does not exist", row.tag_id),
+                _ => return true,
+            };
+            let source = posts.get(&row.post_id).cloned().flatten();
+            dangling.push((format!("posts_tags row {}", row.id), source, missing));
+            !fix
+        });
+        data.posts_authors.retain(|row| {
+            let missing = match (posts.contains_key(&row.post_id), users.contains(&row.author_id)) {
+                (false, _) => format!("post_id {} does not exist", row.post_id),
+                (_, false) => format!("author_id {} does not exist", row.author_id),
+                _ => return true,
+            };
+            let source = posts.get(&row.post_id).cloned().flatten();
+            dangling.push((format!("posts_authors row {}", row.id), source, missing));
+            !fix
+        });
+        data.posts_products.retain(|row| {
+            let missing = match (posts.contains_key(&row.post_id), products.contains(&row.product_id)) {
+                (false, _) => format!("post_id {} does not exist", row.post_id),
+                (_, false) => format!("product_id {} does not exist", row.product_id),
+                _ => return true,
+            };
+            let source = posts.get(&row.post_id).cloned().flatten();
+            dangling.push((format!("posts_products row {}", row.id), source, missing));
+            !fix
+        });
+        data.posts_meta.retain(|row| {
+            if posts.contains_key(&row.post_id) {
+                return true;
+            }
+            let missing = format!("post_id {} does not exist", row.post_id);
+            dangling.push((format!("posts_meta row {}", row.id), None, missing));
+            !fix
+        });
+        // Imports without a roles table leave role ids to the site's roles
+        let roles: HashSet<i32> = data.roles.iter().map(|r| r.id).collect();
+        data.roles_users.retain(|row| {
+            let missing = if !users.contains(&row.user_id) {
+                format!("user_id {} does not exist", row.user_id)
+            } else if !roles.is_empty() && !roles.contains(&row.role_id) {
+                format!("role_id {} does not exist", row.role_id)
+            } else {
+                return true;
+            };
+            dangling.push((format!("roles_users row {}", row.id), None, missing));
+            !fix
+        });
+        for (subject, source, message) in dangling {
+            self.record(fix, Severity::Error, subject, source.as_deref(), message);
+        }
+
+        for post in &mut data.posts {
+            if let Some(author_id) = post.author_id.filter(|id| !users.contains(id)) {
+                if fix {
+                    post.author_id = None;
+                }
+                let message = format!("author_id {} does not exist", author_id);
+                self.record(fix, Severity::Error, post_subject(post), post.source_path.as_deref(), message);
+            }
+        }
+    }
+
+    fn check_lengths(&mut self, import: &mut GhostImport) {
+        let fix = self.fix;
+        let mut found = Vec::new();
+
+        for post in &mut import.data.posts {
+            let subject = post_subject(post);
+            let source = post.source_path.clone();
+            let fields = [
+                Some(&mut post.title),
+                Some(&mut post.slug),
+                post.custom_excerpt.as_mut(),
+                post.meta_title.as_mut(),
+                post.meta_description.as_mut(),
+            ];
+            for ((column, limit), value) in POST_LIMITS.into_iter().zip(fields) {
+                if let Some(message) = value.and_then(|v| check_length(v, column, limit, fix)) {
+                    found.push((subject.clone(), source.clone(), message));
+                }
+            }
+        }
+        let posts: HashMap<i32, (String, Option<String>)> = import
+            .data
+            .posts
+            .iter()
+            .map(|p| (p.id, (post_subject(p), p.source_path.clone())))
+            .collect();
+        for meta in &mut import.data.posts_meta {
+            let (subject, source) = posts
+                .get(&meta.post_id)
+                .cloned()
+                .unwrap_or_else(|| (format!("posts_meta row {}", meta.id), None));
+            let fields = [
+                meta.meta_title.as_mut(),
+                meta.meta_description.as_mut(),
+                meta.og_title.as_mut(),
+                meta.og_description.as_mut(),
+                meta.twitter_title.as_mut(),
+                meta.twitter_description.as_mut(),
+                meta.email_subject.as_mut(),
+                meta.feature_image_alt.as_mut(),
+            ];
+            for ((column, limit), value) in POSTS_META_LIMITS.into_iter().zip(fields) {
+                if let Some(message) = value.and_then(|v| check_length(v, column, limit, fix)) {
+                    found.push((subject.clone(), source.clone(), message));
+                }
+            }
+        }
+        for tag in &mut import.data.tags {
+            let subject = format!("tag {:?}", tag.slug);
+            let fields = [
+                Some(&mut tag.name),
+                Some(&mut tag.slug),
+                tag.description.as_mut(),
+                tag.meta_title.as_mut(),
+                tag.meta_description.as_mut(),
+            ];
+            for ((column, limit), value) in TAG_LIMITS.into_iter().zip(fields) {
+                if let Some(message) = value.and_then(|v| check_length(v, column, limit, fix)) {
+                    found.push((subject.clone(), None, message));
+                }
+            }
+        }
+        for user in &mut import.data.users {
+            let subject = format!("user {:?}", user.slug);
+            let fields = [
+                Some(&mut user.name),
+                Some(&mut user.slug),
+                user.bio.as_mut(),
+                user.location.as_mut(),
+            ];
+            for ((column, limit), value) in USER_LIMITS.into_iter().zip(fields) {
+                if let Some(message) = value.and_then(|v| check_length(v, column, limit, fix)) {
+                    found.push((subject.clone(), None, message));
+                }
+            }
+        }
+
+        for (subject, source, message) in found {
+            self.record(fix, Severity::Error, subject, source.as_deref(), message);
+        }
+    }
+
+    fn check_slugs(&mut self, import: &mut GhostImport) {
+        let data = &mut import.data;
+        // Posts and pages share one slug namespace
+        let posts = data.posts.iter_mut().map(|p| {
+            let subject = post_subject(p);
+            (subject, p.source_path.clone(), &mut p.slug)
+        });
+        self.dedupe_slugs("post", posts.collect());
+        let tags = data.tags.iter_mut().map(|t| (format!("tag {:?}", t.name), None, &mut t.slug));
+        self.dedupe_slugs("tag", tags.collect());
+        let users = data.users.iter_mut().map(|u| (format!("user {:?}", u.name), None, &mut u.slug));
+        self.dedupe_slugs("user", users.collect());
+    }
+
+    fn dedupe_slugs(&mut self, kind: &str, records: Vec<(String, Option<String>, &mut String)>) {
+        let mut taken: HashSet<String> = records.iter().map(|(_, _, slug)| slug.to_string()).collect();
+        let mut seen = HashSet::new();
+        for (subject, source, slug) in records {
+            if seen.insert(slug.clone()) {
+                continue;
+            }
+            let mut message = format!("slug {:?} is used by an earlier {}", slug, kind);
+            if self.fix {
+                *slug = next_free_slug(slug, &taken);
+                taken.insert(slug.clone());
+                seen.insert(slug.clone());
+                message.push_str(&format!("; renamed to {:?}", slug));
+            }
+            self.record(self.fix, Severity::Error, subject, source.as_deref(), message);
+        }
+    }
+
+    /// Unknown statuses are fixed by importing the post as a draft, which
+    /// is the one choice that publishes nothing by accident.
+    fn check_statuses(&mut self, import: &mut GhostImport) {
+        for post in &mut import.data.posts {
+            let Err(message) = PostStatus::try_from(post.status.clone()) else {
+                continue;
+            };
+            let mut message = message;
+            if self.fix {
+                post.status = PostStatus::Draft.name().to_string();
+                message.push_str("; imported as a draft");
+            }
+            let subject = post_subject(post);
+            self.record(self.fix, Severity::Error, subject, post.source_path.as_deref(), message);
+        }
+    }
+
+    /// Unknown visibilities are fixed by restricting the post to paying
+    /// members, so nothing meant for them becomes public by accident.
+    fn check_visibility(&mut self, import: &mut GhostImport) {
+        let with_tiers: HashSet<i32> = import.data.posts_products.iter().map(|r| r.post_id).collect();
+        for post in &mut import.data.posts {
+            let subject = post_subject(post);
+            match PostVisibility::try_from(post.visibility.clone()) {
+                Ok(PostVisibility::Tiers) if !with_tiers.contains(&post.id) => {
+                    let message = "visibility is \"tiers\" but no tier is attached, so no member can read it"
+                        .to_string();
+                    self.record(false, Severity::Warning, subject, post.source_path.as_deref(), message);
+                }
+                Ok(_) => {}
+                Err(mut message) => {
+                    if self.fix {
+                        post.visibility = PostVisibility::Paid.name().to_string();
+                        message.push_str("; restricted to paid members");
+                    }
+                    self.record(self.fix, Severity::Error, subject, post.source_path.as_deref(), message);
+                }
+            }
+        }
+    }
+
+    fn check_emails(&mut self, import: &GhostImport) {
+        let mut seen = HashSet::new();
+        for user in &import.data.users {
+            let subject = format!("user {:?}", user.slug);
+            let email = user.email.to_lowercase();
+            let length = email.chars().count();
+            if length > EMAIL_LIMIT {
+                let message = format!("email is {} characters, Ghost allows {}", length, EMAIL_LIMIT);
+                self.record(false, Severity::Error, subject, None, message);
+            } else if !is_valid_email(&email) {
+                let message = format!("{:?} is not a valid email address", user.email);
+                self.record(false, Severity::Error, subject, None, message);
+            } else if !seen.insert(email.clone()) {
+                let message = format!("email {:?} is used by an earlier user", user.email);
+                self.record(false, Severity::Error, subject, None, message);
+            } else if email.ends_with("@example.com") {
+                let message = format!(
+                    "{:?} is a placeholder; give the author a real address so they can sign in",
+                    user.email
+                );
+                self.record(false, Severity::Warning, subject, None, message);
+            }
+        }
+    }
+
+    fn check_roles(&mut self, import: &GhostImport) {
+        let data = &import.data;
+        let admins: HashSet<i32> = data
+            .roles
+            .iter()
+            .filter(|r| r.name == "Administrator" || r.name == "Owner")
+            .map(|r| r.id)
+            .collect();
+        if !data.users.is_empty() && !data.roles_users.iter().any(|r| admins.contains(&r.role_id)) {
+            self.record(
+                false,
+                Severity::Warning,
+                "users".to_string(),
+                None,
+                "none of them is an Administrator; give one the role with --role or the author registry"
+                    .to_string(),
+            );
+        }
+    }
+}
+
+fn post_subject(post: &Post) -> String {
+    format!("{} {:?}", post.r#type, post.slug)
+}
+
+/// Reports a value longer than `limit` characters, cutting it down when
+/// `fix` is set.
+fn check_length(value: &mut String, column: &str, limit: usize, fix: bool) -> Option<String> {
+    let length = value.chars().count();
+    if length <= limit {
+        return None;
+    }
+    if fix && column == "slug" {
+        truncate_slug(value, limit);
+    } else if fix {
+        truncate(value, limit);
+    }
+    Some(format!("{} is {} characters, Ghost allows {}", column, length, limit))
+}
+
+fn truncate(value: &mut String, limit: usize) {
+    if let Some((index, _)) = value.char_indices().nth(limit) {
+        value.truncate(index);
+    }
+    value.truncate(value.trim_end().len());
+}
+
+fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.chars().any(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ghost::*;
+
+    fn import() -> GhostImport {
+        let post = |id: i32, slug: &str| Post {
+            id,
+            title: slug.to_string(),
+            slug: slug.to_string(),
+            source_path: Some(format!("posts/{}.md", slug)),
+            ..Default::default()
+        };
+        let mut long = post(3, &"a".repeat(200));
+        long.title = "é".repeat(300);
+        long.custom_excerpt = Some("x".repeat(301));
+        long.status = "publish".to_string();
+        let mut tiers_only = post(1, "hello");
+        tiers_only.visibility = "tiers".to_string();
+        let mut vip = post(2, "hello");
+        vip.visibility = "VIP".to_string();
+
+        GhostImport {
+            meta: Meta::default(),
+            data: Data {
+                posts: vec![tiers_only, vip, long],
+                tags: vec![Tag { id: 1, name: "Rust".into(), slug: "rust".into(), ..Default::default() }],
+                users: vec![
+                    User { id: 1, name: "Jane".into(), slug: "jane".into(), email: "jane@example.org".into(), ..Default::default() },
+                    User { id: 2, name: "Bob".into(), slug: "bob".into(), email: "bob@example.com".into(), ..Default::default() },
+                    User { id: 3, name: "Eve".into(), slug: "eve".into(), email: "not an email".into(), ..Default::default() },
+                ],
+                posts_tags: vec![
+                    PostsTags { id: 1, post_id: 1, tag_id: 1, sort_order: 0 },
+                    PostsTags { id: 2, post_id: 2, tag_id: 9, sort_order: 0 },
+                ],
+                posts_authors: vec![PostsAuthors { id: 1, post_id: 1, author_id: 1, sort_order: 0 }],
+                roles_users: Vec::new(),
+                roles: Vec::new(),
+                products: Vec::new(),
+                posts_products: vec![PostsProducts { id: 1, post_id: 1, product_id: 9, sort_order: 0 }],
+                posts_meta: vec![
+                    PostsMeta { id: 1, post_id: 3, og_description: Some("d".repeat(501)), ..Default::default() },
+                    PostsMeta { id: 2, post_id: 7, ..Default::default() },
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_problems() {
+        let mut import = import();
+        let report = Validator::new(false).run(&mut import);
+
+        let lines: Vec<String> = report.issues.iter().map(|i| i.to_string()).collect();
+        assert!(lines.contains(&"error: posts_tags row 2 (posts/hello.md): tag_id 9 does not exist".to_string()));
+        assert!(lines.iter().any(|l| l.contains("title is 300 characters, Ghost allows 255")));
+        assert!(lines.iter().any(|l| l.contains("slug is 200 characters, Ghost allows 191")));
+        assert!(lines.iter().any(|l| l.contains("custom_excerpt is 301 characters")));
+        assert!(lines.contains(&"error: post \"hello\" (posts/hello.md): slug \"hello\" is used by an earlier post".to_string()));
+        assert!(lines.iter().any(|l| l.starts_with("error: user \"eve\"")));
+        assert!(lines.iter().any(|l| l.starts_with("warning: user \"bob\"")));
+        assert!(lines.iter().any(|l| l.contains("unknown status \"publish\"")));
+        assert!(lines.iter().any(|l| l.contains("posts_products row 1 (posts/hello.md): product_id 9")));
+        assert!(lines.iter().any(|l| l.contains("unknown visibility \"VIP\"")));
+        assert!(lines.iter().any(|l| l.contains("og_description is 501 characters, Ghost allows 500")));
+        assert!(lines.contains(&"error: posts_meta row 2: post_id 7 does not exist".to_string()));
+        assert_eq!(report.errors(), 11);
+        assert!(report.fixed.is_empty());
+        assert_eq!(import.data.posts_tags.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_fixes_what_it_can() {
+        let mut import = import();
+        let report = Validator::new(true).run(&mut import);
+
+        assert_eq!(report.fixed.len(), 10);
+        assert_eq!(report.errors(), 1);
+        assert_eq!(import.data.posts_tags.len(), 1);
+        assert_eq!(import.data.posts[1].slug, "hello-2");
+        assert_eq!(import.data.posts[2].title.chars().count(), 255);
+        assert_eq!(import.data.posts[2].slug.len(), 191);
+        assert_eq!(import.data.posts[2].custom_excerpt.as_deref().map(str::len), Some(300));
+        assert_eq!(import.data.posts[2].status, "draft");
+        assert_eq!(import.data.posts[1].visibility, "paid");
+        assert!(import.data.posts_products.is_empty());
+        assert_eq!(import.data.posts_meta.len(), 1);
+        assert_eq!(import.data.posts_meta[0].og_description.as_deref().map(str::len), Some(500));
+        assert!(report.issues.iter().any(|i| i.to_string().contains("no tier is attached")));
+
+        let again = Validator::new(false).run(&mut import);
+        assert_eq!(again.errors(), 1);
+    }
+}
@@ -18,14 +18,14 @@ impl FileOps {
                 .filter_map(|e| e.ok())
             {
                 let path = entry.path();
-                if path.is_file() && path.extension().map_or(false, |ext| ext == extension) {
+                if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
                     files.push(path.to_path_buf());
                 }
             }
         } else {
             for entry in std::fs::read_dir(root_path)? {
                 let path = entry?.path();
-                if path.is_file() && path.extension().map_or(false, |ext| ext == extension) {
+                if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
                     files.push(path);
                 }
             }
@@ -44,7 +44,7 @@ impl FileOps {
     }
 
     pub fn ensure_extension(path: &Path, extension: &str) -> PathBuf {
-        if path.extension().map_or(true, |ext| ext != extension) {
+        if path.extension().is_none_or(|ext| ext != extension) {
             let mut new_path = path.to_path_buf();
             new_path.set_extension(extension);
             new_path
@@ -93,7 +93,7 @@ impl FileOps {
                 .filter_map(|e| e.ok())
             {
                 let path = entry.path();
-                if path.is_file() && Self::is_image_file(&path) {
+                if path.is_file() && Self::is_image_file(path) {
                     files.push(path.to_path_buf());
                 }
             }